@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use eyre::Context;
+use eyre::bail;
+use tracing::info;
+
+pub const VERSION_NAME_PLACEHOLDER: &str = "{{version_name}}";
+pub const CRATE_NAME_PLACEHOLDER: &str = "{{crate_name}}";
+
+/// The files of a version started from scratch, relative to the version dir.
+pub const BLANK_VERSION_FILES: [(&str, &str); 6] = [
+    (
+        "Cargo.toml",
+        include_str!("../blank_version/Cargo.toml.tmpl"),
+    ),
+    ("src/main.rs", include_str!("../blank_version/main.rs.tmpl")),
+    ("src/lib.rs", include_str!("../blank_version/lib.rs.tmpl")),
+    (
+        "rustfmt.toml",
+        include_str!("../blank_version/rustfmt.toml.tmpl"),
+    ),
+    (
+        ".gitignore",
+        include_str!("../blank_version/gitignore.tmpl"),
+    ),
+    (
+        "next steps.md",
+        include_str!("../blank_version/next steps.md.tmpl"),
+    ),
+];
+
+/// Fill in the version name, and the crate name derived from it, in a template file.
+pub fn render_blank_version_file(template: &str, version_name: &str) -> String {
+    template
+        .replace(VERSION_NAME_PLACEHOLDER, version_name)
+        .replace(CRATE_NAME_PLACEHOLDER, &version_name.replace('-', "_"))
+}
+
+/// Write the blank version files into `version_dir`, refusing to overwrite any that exist.
+pub async fn scaffold_blank_version(version_dir: &Path, version_name: &str) -> eyre::Result<()> {
+    for (relative_path, _) in BLANK_VERSION_FILES {
+        let path = version_dir.join(relative_path);
+        if tokio::fs::try_exists(&path).await? {
+            bail!("{} already exists", path.display());
+        }
+    }
+    for (relative_path, template) in BLANK_VERSION_FILES {
+        let path = version_dir.join(relative_path);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, render_blank_version_file(template, version_name))
+            .await
+            .context(format!("Writing {}", path.display()))?;
+    }
+    info!(
+        "Scaffolded blank version {} in {}",
+        version_name,
+        version_dir.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo_toml::CargoToml;
+
+    #[tokio::test]
+    async fn scaffolds_a_rendered_version() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let version_dir = dir.path().join("v037-blank-start");
+        scaffold_blank_version(&version_dir, "v037-blank-start").await?;
+
+        let cargo_toml: CargoToml =
+            toml::from_str(&tokio::fs::read_to_string(version_dir.join("Cargo.toml")).await?)?;
+        assert_eq!(
+            cargo_toml.package.map(|x| x.name).as_deref(),
+            Some("v037-blank-start")
+        );
+        let main_rs = tokio::fs::read_to_string(version_dir.join("src/main.rs")).await?;
+        assert!(main_rs.contains("v037_blank_start::run()"), "{main_rs}");
+        for (relative_path, _) in BLANK_VERSION_FILES {
+            let content = tokio::fs::read_to_string(version_dir.join(relative_path)).await?;
+            assert!(
+                !content.contains(VERSION_NAME_PLACEHOLDER)
+                    && !content.contains(CRATE_NAME_PLACEHOLDER),
+                "{relative_path} was not rendered"
+            );
+        }
+
+        assert!(
+            scaffold_blank_version(&version_dir, "v037-blank-start")
+                .await
+                .is_err(),
+            "existing files are not overwritten"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,426 @@
+pub mod blank_version;
+pub mod logging;
+pub mod prompt;
+pub mod version_name;
+
+use blank_version::scaffold_blank_version;
+use eyre::Context;
+use eyre::OptionExt;
+use eyre::bail;
+use eyre::eyre;
+use logging::LogOptions;
+use logging::init_logging;
+use logging::install_error_hook;
+use prompt::answer;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use tracing::info;
+use tracing::warn;
+use version_name::VersionName;
+use version_name::get_recommended_suffix;
+use version_name::validate_next_version_name;
+
+pub async fn init() -> eyre::Result<()> {
+    init_with_log_options(&LogOptions::default()).await
+}
+
+pub async fn init_with_log_options(log_options: &LogOptions) -> eyre::Result<()> {
+    install_error_hook()?;
+    init_logging(log_options)?;
+
+    info!("Ahoy, world!");
+    Ok(())
+}
+
+pub fn is_valid_version_name(version_name: &str) -> eyre::Result<()> {
+    version_name.parse::<VersionName>()?;
+    Ok(())
+}
+
+pub async fn get_versions(
+    nanuak_dictionary_root_dir: impl AsRef<Path> + Sized,
+) -> eyre::Result<Vec<PathBuf>> {
+    // Return the child directories sorted by version number, so v1000 comes after v999.
+    let mut children = tokio::fs::read_dir(nanuak_dictionary_root_dir).await?;
+    let mut versions = Vec::new();
+    while let Some(entry) = children.next_entry().await? {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if let Ok(version_name) = VersionName::try_from(&path) {
+            versions.push((version_name, path));
+        }
+    }
+    versions.sort();
+    Ok(versions.into_iter().map(|(_, path)| path).collect())
+}
+
+pub async fn is_valid_nanuak_dictionary_root_dir(
+    nanuak_dictionary_root_dir: impl AsRef<Path>,
+) -> bool {
+    // Return true if the path is a directory with the exact name "Nanuak-Dictionary"
+    let path = nanuak_dictionary_root_dir.as_ref();
+    path.is_dir() && path.file_name() == Some(OsStr::new("Nanuak-Dictionary"))
+}
+
+pub async fn get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir()
+-> eyre::Result<PathBuf> {
+    let current_dir = tokio::fs::canonicalize(".").await?;
+    if is_valid_nanuak_dictionary_root_dir(&current_dir).await {
+        Ok(current_dir)
+    } else {
+        let parent_dir = current_dir
+            .parent()
+            .ok_or_else(|| eyre!("No parent directory"))?;
+        if is_valid_nanuak_dictionary_root_dir(&parent_dir).await {
+            Ok(parent_dir.to_path_buf())
+        } else {
+            Err(eyre!("No Nanuak-Dictionary directory found"))
+        }
+    }
+}
+
+pub async fn extract_next_version_number(versions: &[PathBuf]) -> eyre::Result<usize> {
+    // Take the highest version number rather than the last entry, which may not be sorted numerically.
+    let highest_version_number = versions
+        .iter()
+        .map(VersionName::try_from)
+        .collect::<eyre::Result<Vec<_>>>()?
+        .into_iter()
+        .map(|x| x.number)
+        .max()
+        .ok_or_else(|| eyre!("No versions found"))?;
+    Ok(highest_version_number + 1)
+}
+
+pub async fn extract_version_number(version_name: &str) -> eyre::Result<usize> {
+    let x = version_name
+        .parse::<VersionName>()
+        .context(format!("Failed to parse version number from {version_name:?}"))?;
+    Ok(x.number)
+}
+
+pub async fn prompt_next_version_name(next_version_number: usize) -> eyre::Result<String> {
+    // Prompt the user for the next version name.
+    let next_version_name = answer(
+        &format!("Enter the name for version v{}", next_version_number),
+        |prompt| {
+            Ok(dialoguer::Input::<String>::new()
+                .with_prompt(prompt)
+                .interact()?)
+        },
+    )?;
+    Ok(next_version_name)
+}
+
+/// Prompt for the next version name, offering `v{number}-{suffix}` as the default when a suffix is recommended.
+pub async fn prompt_next_version_name_with_recommended_suffix(
+    next_version_number: usize,
+    recommended_suffix: Option<&str>,
+) -> eyre::Result<String> {
+    let recommended = VersionName::new(
+        next_version_number,
+        recommended_suffix.map(|x| x.to_string()),
+    );
+    let next_version_name = answer(
+        &format!("Enter the name for version v{:03}", next_version_number),
+        |prompt| {
+            Ok(dialoguer::Input::<String>::new()
+                .with_prompt(prompt)
+                .default(recommended.to_string())
+                .interact_text()?)
+        },
+    )?;
+    Ok(next_version_name)
+}
+
+#[async_recursion::async_recursion]
+pub async fn copy_dir_all<S, D>(src: S, dst: D) -> Result<(), std::io::Error>
+where
+    S: AsRef<Path> + Send + Sync,
+    D: AsRef<Path> + Send + Sync,
+{
+    tokio::fs::create_dir_all(&dst).await?;
+    let mut entries = tokio::fs::read_dir(src).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let ty = entry.file_type().await?;
+        if ty.is_dir() {
+            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))
+                .await?;
+        } else {
+            tokio::fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn create_new_version() -> eyre::Result<()> {
+    info!("Find the root dir containing the versions");
+    let nanuak_dictionary_root_dir =
+        get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+
+    info!("Get the version objects");
+    let versions = get_versions(&nanuak_dictionary_root_dir).await?;
+
+    info!("Print them");
+    for version in &versions {
+        println!("{}", version.display());
+    }
+
+    info!("Get the version number to be used in the name of the next version");
+    let next_version_number = extract_next_version_number(&versions)
+        .await
+        .context(format!("Extracting next version number from {versions:?}"))?;
+
+    info!("Checking if the user wants to accept all suggested defaults");
+    let accept_all_defaults = answer("Accept all defaults?", |prompt| {
+        Ok(dialoguer::Confirm::new().with_prompt(prompt).interact()?)
+    })?;
+    if accept_all_defaults {
+        info!("User accepted all defaults");
+        let next_version_name = format!("v{:03}", next_version_number);
+        let next_version_dir = nanuak_dictionary_root_dir.join(&next_version_name);
+        if next_version_dir.exists() {
+            bail!(
+                "Directory {} already exists. Cannot proceed with all defaults",
+                next_version_dir.display()
+            );
+        }
+        scaffold_blank_version(&next_version_dir, &next_version_name).await?;
+        add_version_to_workspace_cargo_toml(&next_version_dir, &next_version_name)
+            .await
+            .context("Adding the new version to the workspace Cargo.toml")?;
+        return Ok(());
+    }
+
+    info!("Ask them if they would like to copy an existing version as a starter");
+    let copy_existing_version = answer(
+        "Would you like to copy an existing version as a starter?",
+        |prompt| Ok(dialoguer::Confirm::new().with_prompt(prompt).interact()?),
+    )?;
+    let version_to_copy = if copy_existing_version {
+        info!("List the existing versions");
+        for (i, version) in versions.iter().enumerate() {
+            println!("{}: {}", i, version.display());
+        }
+        info!("Prompt the user for the index of the version to copy");
+        let version_to_copy_index = answer("Enter the index of the version to copy", |prompt| {
+            Ok(dialoguer::Input::<usize>::new()
+                .with_prompt(prompt)
+                .interact()?)
+        })?;
+        Some(
+            versions
+                .get(version_to_copy_index)
+                .ok_or_eyre(format!("No version at index {version_to_copy_index}"))?
+                .clone(),
+        )
+    } else {
+        None
+    };
+
+    info!("Recommend the suffix of the version being copied");
+    let recommended_suffix = version_to_copy
+        .as_ref()
+        .and_then(|x| x.file_name())
+        .and_then(|x| get_recommended_suffix(&x.to_string_lossy()));
+
+    info!("Prompt the user for the name of the next version, hinting the next version number");
+    let mut user_supplied_next_version_name = prompt_next_version_name_with_recommended_suffix(
+        next_version_number,
+        recommended_suffix.as_deref(),
+    )
+    .await?;
+
+    info!("Repeat prompt until valid input received");
+    while let Err(e) = validate_next_version_name(&user_supplied_next_version_name, &versions) {
+        warn!("Error: {:#}", e);
+        user_supplied_next_version_name = prompt_next_version_name_with_recommended_suffix(
+            next_version_number,
+            recommended_suffix.as_deref(),
+        )
+        .await?;
+    }
+    let validated_next_version_name = user_supplied_next_version_name;
+
+    info!("Identify the next version directory path");
+    let next_version_dir = nanuak_dictionary_root_dir.join(&validated_next_version_name);
+
+    if let Some(version_to_copy) = &version_to_copy {
+        info!("Copy the selected version to the new version directory");
+        copy_dir_all(version_to_copy, &next_version_dir)
+            .await
+            .context("Copying the reference version to the new version")?;
+        info!(
+            "Copied version {} to new version directory",
+            version_to_copy.display()
+        );
+
+        info!("Applying the new version name to the Cargo.toml");
+        apply_file_changes_for_new_version_name(next_version_dir, &validated_next_version_name)
+            .await
+            .context("Applying the new version name to the Cargo.toml")?;
+    } else {
+        info!("Scaffold a blank version in the new version directory");
+        scaffold_blank_version(&next_version_dir, &validated_next_version_name).await?;
+        add_version_to_workspace_cargo_toml(&next_version_dir, &validated_next_version_name)
+            .await
+            .context("Adding the new version to the workspace Cargo.toml")?;
+    }
+
+    info!("Done");
+    Ok(())
+}
+
+pub async fn apply_file_changes_for_new_version_name(
+    new_version_dir: PathBuf,
+    new_version_name: &str,
+) -> eyre::Result<()> {
+    info!(
+        "replace the old version name in {}/Cargo.toml with the new version name",
+        new_version_name
+    );
+    let cargo_toml_path = new_version_dir.join("Cargo.toml");
+    let cargo_toml = tokio::fs::read_to_string(&cargo_toml_path)
+        .await
+        .context(format!(
+            "Reading {} as CargoToml",
+            cargo_toml_path.display()
+        ))?;
+    let mut cargo_toml: cargo_toml::CargoToml = toml::from_str(&cargo_toml).context(format!(
+        "Parsing {} as CargoToml",
+        cargo_toml_path.display()
+    ))?;
+    cargo_toml
+        .package
+        .as_mut()
+        .ok_or_eyre(format!(
+            "Expected \"package\" to be present in {}",
+            cargo_toml_path.display()
+        ))?
+        .name = new_version_name.to_string();
+    tokio::fs::write(&cargo_toml_path, toml::to_string(&cargo_toml)?).await?;
+
+    add_version_to_workspace_cargo_toml(&new_version_dir, new_version_name).await
+}
+
+/// Add the version as a member and a path dependency of the workspace it sits in.
+pub async fn add_version_to_workspace_cargo_toml(
+    new_version_dir: &Path,
+    new_version_name: &str,
+) -> eyre::Result<()> {
+    let workspace_cargo_toml_path = new_version_dir
+        .parent()
+        .ok_or_eyre("Could not get parent of new version directory")?
+        .join("Cargo.toml");
+    info!(
+        "Add the new version name to {}",
+        workspace_cargo_toml_path.display()
+    );
+    let workspace_cargo_toml = tokio::fs::read_to_string(&workspace_cargo_toml_path).await?;
+    let mut workspace_cargo_toml: cargo_toml::CargoToml = toml::from_str(&workspace_cargo_toml)
+        .context(format!(
+            "Interpreting cargo toml from {}",
+            workspace_cargo_toml_path.display()
+        ))?;
+    let new_version_name = new_version_name.to_string();
+    workspace_cargo_toml
+        .workspace
+        .as_mut()
+        .ok_or_eyre("No workspace")?
+        .members
+        .push(new_version_name.clone());
+    let None = workspace_cargo_toml
+        .workspace
+        .as_mut()
+        .ok_or_eyre("No workspace")?
+        .dependencies
+        .0
+        .insert(
+            new_version_name.clone(),
+            cargo_toml::Dependency::Path {
+                path: new_version_name.to_string(),
+                features: None,
+            },
+        )
+    else {
+        bail!(
+            "Dependency already exists trying to insert {:?} to {} which exists as {:#?}",
+            new_version_name,
+            workspace_cargo_toml_path.display(),
+            workspace_cargo_toml
+        );
+    };
+    tokio::fs::write(
+        &workspace_cargo_toml_path,
+        toml::to_string(&workspace_cargo_toml)?,
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub mod cargo_toml {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+    use serde::Serialize;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    pub struct CargoToml {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub package: Option<Package>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub dependencies: Option<Dependencies>,
+        #[serde(
+            rename = "dev-dependencies",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub dev_dependencies: Option<Dependencies>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub workspace: Option<Workspace>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct Package {
+        pub name: String,
+        pub version: String,
+        pub edition: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct Workspace {
+        pub resolver: String,
+        pub members: Vec<String>,
+        pub dependencies: Dependencies,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    pub struct Dependencies(pub HashMap<String, Dependency>);
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(untagged)]
+    pub enum Dependency {
+        Path {
+            path: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            features: Option<Vec<String>>,
+        },
+        RawVersion(String),
+        Version {
+            version: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            features: Option<Vec<String>>,
+        },
+        GitRevision {
+            git: String,
+            rev: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            features: Option<Vec<String>>,
+        },
+    }
+}
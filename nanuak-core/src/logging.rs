@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::Subscriber;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Log files are named this followed by the date they were started.
+pub const LOG_FILE_NAME_PREFIX: &str = "nanuak.log";
+
+/// How log lines are laid out.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Full,
+    Compact,
+    Pretty,
+    /// One JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogOptions {
+    /// Used when `RUST_LOG` is not set.
+    pub level: LevelFilter,
+    pub format: LogFormat,
+    /// Also write to a file in this dir, starting a new one each day.
+    pub file_dir: Option<PathBuf>,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::DEBUG,
+            format: LogFormat::default(),
+            file_dir: None,
+        }
+    }
+}
+
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::OFF,
+    LevelFilter::ERROR,
+    LevelFilter::WARN,
+    LevelFilter::INFO,
+    LevelFilter::DEBUG,
+    LevelFilter::TRACE,
+];
+
+/// Move `level` up by `verbosity` steps for each `-v`, or down for each `-q` when negative.
+pub fn adjust_level(level: LevelFilter, verbosity: i8) -> LevelFilter {
+    let current = LEVELS.iter().position(|x| *x == level).unwrap_or(3) as i8;
+    LEVELS[(current + verbosity).clamp(0, LEVELS.len() as i8 - 1) as usize]
+}
+
+fn get_fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Install color-eyre, before anything makes an `eyre::Report` and installs the plain handler.
+pub fn install_error_hook() -> eyre::Result<()> {
+    color_eyre::install()
+}
+
+/// Install a tracing subscriber writing to stderr, and to a log file if asked.
+pub fn init_logging(options: &LogOptions) -> eyre::Result<()> {
+    let filter = EnvFilter::builder()
+        .with_default_directive(options.level.into())
+        .from_env()?;
+    let mut layers = vec![get_fmt_layer(options.format, std::io::stderr, true)];
+    if let Some(dir) = &options.file_dir {
+        let appender = tracing_appender::rolling::daily(dir, LOG_FILE_NAME_PREFIX);
+        layers.push(get_fmt_layer(options.format, appender, false));
+    }
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layers)
+        .init();
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use eyre::Context;
+use eyre::bail;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::info;
+
+/// One answer given to a prompt, as stored in an answers file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PromptAnswer {
+    pub prompt: String,
+    pub answer: serde_json::Value,
+    /// The user asked to go back instead of answering.
+    #[serde(default)]
+    pub went_back: bool,
+}
+
+/// Returned by a prompt when the user asks to go back to the previous step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoBack;
+
+impl std::fmt::Display for GoBack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Going back to the previous step")
+    }
+}
+
+impl std::error::Error for GoBack {}
+
+/// Typed into a text prompt to go back.
+pub const BACK_INPUT: &str = ":back";
+
+static BACK_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Whether prompts should tell the user they can go back, set by whoever drives the states.
+pub fn set_back_allowed(allowed: bool) {
+    BACK_ALLOWED.store(allowed, Ordering::Relaxed);
+}
+
+pub fn is_back_allowed() -> bool {
+    BACK_ALLOWED.load(Ordering::Relaxed)
+}
+
+static ANSWER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// How many prompts have been answered so far, letting callers tell whether a step asked anything.
+pub fn get_answer_count() -> usize {
+    ANSWER_COUNT.load(Ordering::Relaxed)
+}
+
+pub fn is_go_back(error: &eyre::Report) -> bool {
+    error.chain().any(|cause| cause.is::<GoBack>())
+}
+
+#[derive(Debug)]
+enum PromptMode {
+    Live,
+    Recording(PathBuf),
+    Replaying(VecDeque<PromptAnswer>),
+}
+
+static PROMPT_MODE: Mutex<PromptMode> = Mutex::new(PromptMode::Live);
+
+/// Append every answer given from now on to `path` as JSON lines.
+pub fn record_answers_to(path: impl Into<PathBuf>) {
+    *PROMPT_MODE.lock().unwrap() = PromptMode::Recording(path.into());
+}
+
+/// Answer prompts from `answers` in order instead of asking the user.
+pub fn replay_answers(answers: Vec<PromptAnswer>) {
+    *PROMPT_MODE.lock().unwrap() = PromptMode::Replaying(answers.into());
+}
+
+/// How many replayed answers have not been asked for yet.
+pub fn get_remaining_answer_count() -> usize {
+    match &*PROMPT_MODE.lock().unwrap() {
+        PromptMode::Replaying(answers) => answers.len(),
+        _ => 0,
+    }
+}
+
+pub async fn read_answers(path: impl AsRef<Path>) -> eyre::Result<Vec<PromptAnswer>> {
+    let path = path.as_ref();
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .context(format!("Reading answers from {}", path.display()))?;
+    let mut answers = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = serde_json::from_str(line).context(format!(
+            "Parsing line {} of {}",
+            i + 1,
+            path.display()
+        ))?;
+        answers.push(answer);
+    }
+    Ok(answers)
+}
+
+/// Get the answer to `prompt`, passing it to `ask` for the user unless answers are being replayed.
+///
+/// The prompt text is stored next to each recorded answer so a replay that drifts
+/// from the recording fails at the first prompt that differs.
+/// Answering [`BACK_INPUT`] or returning [`GoBack`] from `ask` fails with [`GoBack`].
+pub fn answer<T: Serialize + DeserializeOwned>(
+    prompt: &str,
+    ask: impl FnOnce(&str) -> eyre::Result<T>,
+) -> eyre::Result<T> {
+    answer_with_back_hint(prompt, BACK_INPUT, ask)
+}
+
+/// [`answer`] for prompts that `ask` with `interact_opt`, where Esc gives `None` and goes back.
+pub fn answer_opt<T: Serialize + DeserializeOwned>(
+    prompt: &str,
+    ask: impl FnOnce(&str) -> eyre::Result<Option<T>>,
+) -> eyre::Result<T> {
+    answer_with_back_hint(prompt, "Esc", |prompt| {
+        ask(prompt)?.ok_or_else(|| GoBack.into())
+    })
+}
+
+/// `back_hint` is what the user presses or types to go back, shown when going back is allowed.
+fn answer_with_back_hint<T: Serialize + DeserializeOwned>(
+    prompt: &str,
+    back_hint: &str,
+    ask: impl FnOnce(&str) -> eyre::Result<T>,
+) -> eyre::Result<T> {
+    let recording_path = {
+        let mut mode = PROMPT_MODE.lock().unwrap();
+        match &mut *mode {
+            PromptMode::Live => None,
+            PromptMode::Recording(path) => Some(path.clone()),
+            PromptMode::Replaying(answers) => {
+                let Some(recorded) = answers.pop_front() else {
+                    bail!("Ran out of recorded answers when asked {:?}", prompt);
+                };
+                if recorded.prompt != prompt {
+                    bail!(
+                        "Expected to be asked {:?} but was asked {:?}",
+                        recorded.prompt,
+                        prompt
+                    );
+                }
+                if recorded.went_back {
+                    info!("Replaying going back from {:?}", prompt);
+                    return Err(GoBack.into());
+                }
+                info!("Replaying answer for {:?}: {}", prompt, recorded.answer);
+                ANSWER_COUNT.fetch_add(1, Ordering::Relaxed);
+                return serde_json::from_value(recorded.answer)
+                    .context(format!("Reading the recorded answer for {:?}", prompt));
+            }
+        }
+    };
+    let shown_prompt = match is_back_allowed() {
+        true => format!("{prompt} ({back_hint} to go back)"),
+        false => prompt.to_string(),
+    };
+    let result = ask(&shown_prompt).and_then(|value| {
+        if serde_json::to_value(&value)? == BACK_INPUT {
+            return Err(GoBack.into());
+        }
+        ANSWER_COUNT.fetch_add(1, Ordering::Relaxed);
+        Ok(value)
+    });
+    if let Some(path) = recording_path {
+        let record = match &result {
+            Ok(value) => PromptAnswer {
+                prompt: prompt.to_string(),
+                answer: serde_json::to_value(value)?,
+                went_back: false,
+            },
+            Err(e) if is_go_back(e) => PromptAnswer {
+                prompt: prompt.to_string(),
+                answer: serde_json::Value::Null,
+                went_back: true,
+            },
+            Err(_) => return result,
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(format!("Opening answers file {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    }
+    result
+}
@@ -0,0 +1,198 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use eyre::bail;
+use eyre::eyre;
+
+/// A parsed `vNNN-suffix` version directory name.
+///
+/// Ordering is by number first so `v1000` sorts after `v999`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VersionName {
+    pub number: usize,
+    pub suffix: Option<String>,
+}
+
+impl VersionName {
+    pub fn new(number: usize, suffix: Option<String>) -> Self {
+        Self { number, suffix }
+    }
+}
+
+impl Display for VersionName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{:03}", self.number)?;
+        if let Some(suffix) = &self.suffix {
+            write!(f, "-{suffix}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for VersionName {
+    type Err = eyre::Error;
+
+    fn from_str(version_name: &str) -> Result<Self, Self::Err> {
+        if let Some(bad) = version_name
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-'))
+        {
+            bail!(
+                "Version name {version_name:?} may only contain ASCII letters, digits and hyphens, found {bad:?}"
+            );
+        }
+        let Some(rest) = version_name.strip_prefix('v') else {
+            bail!("Version name {version_name:?} must start with a 'v', like \"v001-example\"");
+        };
+        let (number, suffix) = match rest.split_once('-') {
+            Some((number, suffix)) => (number, Some(suffix)),
+            None => (rest, None),
+        };
+        if number.is_empty() {
+            bail!("Version name {version_name:?} must have a number after the 'v', like \"v001\"");
+        }
+        if !number.chars().all(|c| c.is_ascii_digit()) {
+            bail!(
+                "Version name {version_name:?} must have only digits between the 'v' and the first hyphen, found {number:?}"
+            );
+        }
+        let number = number
+            .parse::<usize>()
+            .map_err(|e| eyre!("Version number {number:?} in {version_name:?} is invalid: {e}"))?;
+        let suffix = match suffix {
+            None => None,
+            Some("") => bail!("Version name {version_name:?} must not end with a hyphen"),
+            Some(suffix) if suffix.split('-').any(|word| word.is_empty()) => {
+                bail!(
+                    "Version name {version_name:?} must not have empty hyphen-separated words in its suffix {suffix:?}"
+                )
+            }
+            Some(suffix) => Some(suffix.to_string()),
+        };
+        Ok(Self { number, suffix })
+    }
+}
+
+impl TryFrom<&PathBuf> for VersionName {
+    type Error = eyre::Error;
+
+    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
+        path.file_name()
+            .ok_or_else(|| eyre!("No file name on {path:?}"))?
+            .to_string_lossy()
+            .parse()
+    }
+}
+
+/// Ensure `next_version_name` parses and that its number is not already taken by one of `versions`.
+pub fn validate_next_version_name(
+    next_version_name: &str,
+    versions: &[PathBuf],
+) -> eyre::Result<VersionName> {
+    let next = next_version_name.parse::<VersionName>()?;
+    for version in versions {
+        let Ok(existing) = VersionName::try_from(version) else {
+            continue;
+        };
+        if existing.number == next.number {
+            bail!(
+                "Version number {} is already used by {}, pick a different number",
+                next.number,
+                existing
+            );
+        }
+    }
+    Ok(next)
+}
+
+/// The suffix a new version should probably keep, taken from the version it is copied from.
+pub fn get_recommended_suffix(template_version_name: &str) -> Option<String> {
+    template_version_name
+        .parse::<VersionName>()
+        .ok()
+        .and_then(|x| x.suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_number_and_suffix() -> eyre::Result<()> {
+        assert_eq!(
+            "v036-dictionary-docker".parse::<VersionName>()?,
+            VersionName::new(36, Some("dictionary-docker".to_string()))
+        );
+        assert_eq!("v007".parse::<VersionName>()?, VersionName::new(7, None));
+        assert_eq!(
+            "v1000-next".parse::<VersionName>()?.to_string(),
+            "v1000-next"
+        );
+        assert_eq!(VersionName::new(7, None).to_string(), "v007");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_names() {
+        for bad in [
+            "",
+            "036-dictionary",
+            "v",
+            "v-dictionary",
+            "v03a-dictionary",
+            "v036-",
+            "v036-dictionary-",
+            "v036--dictionary",
+            "v036_dictionary",
+            "v036 dictionary",
+        ] {
+            assert!(
+                bad.parse::<VersionName>().is_err(),
+                "{bad:?} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn orders_by_number_then_suffix() -> eyre::Result<()> {
+        let mut names = ["v1000", "v999-b", "v999-a", "v999", "v010-z", "v0100"]
+            .into_iter()
+            .map(|x| x.parse::<VersionName>())
+            .collect::<eyre::Result<Vec<_>>>()?;
+        names.sort();
+        let names = names.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["v010-z", "v100", "v999", "v999-a", "v999-b", "v1000"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_a_next_version_reusing_a_number() -> eyre::Result<()> {
+        let versions = vec![
+            PathBuf::from("/ws/v035-dictionary"),
+            PathBuf::from("/ws/v036-dictionary-docker"),
+            PathBuf::from("/ws/nanuak-core"),
+        ];
+        assert!(validate_next_version_name("v036-other", &versions).is_err());
+        assert!(validate_next_version_name("v36", &versions).is_err());
+        assert!(validate_next_version_name("v037-bad-", &versions).is_err());
+        assert_eq!(
+            validate_next_version_name("v037-dictionary", &versions)?,
+            VersionName::new(37, Some("dictionary".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn recommends_the_suffix_of_the_template() {
+        assert_eq!(
+            get_recommended_suffix("v036-dictionary-docker"),
+            Some("dictionary-docker".to_string())
+        );
+        assert_eq!(get_recommended_suffix("v007"), None);
+        assert_eq!(get_recommended_suffix("nanuak-core"), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! `#[derive(State)]` for the state machine enums.
+//!
+//! Each variant is labelled with its name and says how it is described and whether it is
+//! terminal or has side effects:
+//!
+//! ```ignore
+//! #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+//! pub enum ExampleState {
+//!     #[default]
+//!     #[state(describe = "Ask for a word")]
+//!     AskForWord,
+//!     #[state(describe = "Write the word to disk", side_effects, idempotent)]
+//!     WriteWord { word: String },
+//!     #[state(describe = "Check the word", skip = Self::Done)]
+//!     CheckWord { word: String },
+//!     #[state(describe = "Done", terminal)]
+//!     Done,
+//! }
+//!
+//! impl ExampleState {
+//!     async fn transition(self) -> eyre::Result<Self> {
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! Newtype variants wrapping another `State` are delegated to it: they are described as
+//! `"Variant - inner description"`, have side effects when the inner state does, and `next`
+//! steps the inner state and wraps the result. With `#[state(delegate = some_fn)]` on the enum,
+//! `next` calls `some_fn("Variant", inner, Self::Variant).await` instead and the variant is only
+//! terminal when marked so.
+//!
+//! `next = <expr>` on any other variant gives the future of its next state, evaluated with `self`
+//! in scope. Terminal variants without one stay where they are, and the rest are handed to the
+//! enum's own `async fn transition(self) -> eyre::Result<Self>`, which is only needed for them.
+//!
+//! The impl is for `crate::state::State` unless the enum says otherwise with
+//! `#[state(trait_path = some::path::State)]`.
+//!
+//! A variant is safe to retry unless it has side effects, which `idempotent` overrides.
+//! `skip = <expr>` gives the state to carry on from when the user skips the variant after it
+//! failed, evaluated with `self` in scope. Newtype variants skip to wherever the inner state
+//! skips to before falling back on their own `skip`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use quote::quote_spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Expr;
+use syn::Fields;
+use syn::LitStr;
+use syn::Path;
+use syn::Variant;
+use syn::parse_macro_input;
+
+#[proc_macro_derive(State, attributes(state))]
+pub fn derive_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct EnumOptions {
+    delegate: Option<Path>,
+    trait_path: Option<Path>,
+}
+
+#[derive(Default)]
+struct VariantOptions {
+    describe: Option<LitStr>,
+    terminal: bool,
+    side_effects: bool,
+    idempotent: bool,
+    skip: Option<Expr>,
+    next: Option<Expr>,
+}
+
+fn parse_enum_options(input: &DeriveInput) -> syn::Result<EnumOptions> {
+    let mut options = EnumOptions::default();
+    for attr in input.attrs.iter().filter(|x| x.path().is_ident("state")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("delegate") {
+                options.delegate = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("trait_path") {
+                options.trait_path = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `delegate = some_fn` or `trait_path = some::path::State`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn parse_variant_options(variant: &Variant) -> syn::Result<VariantOptions> {
+    let mut options = VariantOptions::default();
+    for attr in variant.attrs.iter().filter(|x| x.path().is_ident("state")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("describe") {
+                options.describe = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("terminal") {
+                options.terminal = true;
+            } else if meta.path.is_ident("side_effects") {
+                options.side_effects = true;
+            } else if meta.path.is_ident("idempotent") {
+                options.idempotent = true;
+            } else if meta.path.is_ident("skip") {
+                options.skip = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("next") {
+                options.next = Some(meta.value()?.parse()?);
+            } else {
+                return Err(
+                    meta.error("expected `describe = \"...\"`, `terminal`, `side_effects`, `idempotent`, `skip = ...` or `next = ...`")
+                );
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn is_newtype(variant: &Variant) -> bool {
+    matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "State can only be derived for enums",
+        ));
+    };
+    let enum_options = parse_enum_options(input)?;
+    let trait_path = match &enum_options.trait_path {
+        Some(trait_path) => quote! { #trait_path },
+        None => quote! { crate::state::State },
+    };
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut label_arms = Vec::new();
+    let mut describe_arms = Vec::new();
+    let mut next_arms = Vec::new();
+    let mut terminal_arms = Vec::new();
+    let mut side_effects_arms = Vec::new();
+    let mut idempotent_arms = Vec::new();
+    let mut skip_arms = Vec::new();
+    let mut needs_transition = false;
+    for variant in &data.variants {
+        let options = parse_variant_options(variant)?;
+        let ident = &variant.ident;
+        let label = ident.to_string();
+        let terminal = options.terminal;
+        let side_effects = options.side_effects;
+        let idempotent = !side_effects || options.idempotent;
+        label_arms.push(match &variant.fields {
+            Fields::Named(_) => quote! { Self::#ident { .. } => #label, },
+            Fields::Unnamed(_) => quote! { Self::#ident(..) => #label, },
+            Fields::Unit => quote! { Self::#ident => #label, },
+        });
+        if is_newtype(variant) {
+            if let Some(next) = &options.next {
+                return Err(syn::Error::new_spanned(
+                    next,
+                    "`next` is for variants that aren't delegated to an inner state",
+                ));
+            }
+            let prefix = options
+                .describe
+                .map(|x| x.value())
+                .unwrap_or_else(|| label.clone());
+            describe_arms.push(quote! {
+                Self::#ident(state) => format!("{} - {}", #prefix, #trait_path::describe(state)),
+            });
+            side_effects_arms.push(match side_effects {
+                true => quote! { Self::#ident(_) => true, },
+                false => quote! { Self::#ident(state) => #trait_path::has_side_effects(state), },
+            });
+            idempotent_arms.push(match options.idempotent {
+                true => quote! { Self::#ident(_) => true, },
+                false => quote! { Self::#ident(state) => #trait_path::is_idempotent(state), },
+            });
+            skip_arms.push(match &options.skip {
+                Some(skip) => quote! {
+                    Self::#ident(state) => #trait_path::skip(state).map(Self::#ident).or_else(|| Some(#skip)),
+                },
+                None => quote! { Self::#ident(state) => #trait_path::skip(state).map(Self::#ident), },
+            });
+            match &enum_options.delegate {
+                Some(delegate) => {
+                    next_arms.push(quote! {
+                        Self::#ident(state) => #delegate(#label, state, Self::#ident).await,
+                    });
+                    terminal_arms.push(quote! {
+                        Self::#ident(_) => #terminal,
+                    });
+                }
+                None => {
+                    next_arms.push(quote! {
+                        Self::#ident(state) => Ok(Self::#ident(#trait_path::next(state).await?)),
+                    });
+                    terminal_arms.push(match terminal {
+                        true => quote! { Self::#ident(_) => true, },
+                        false => quote! { Self::#ident(state) => #trait_path::is_terminal(state), },
+                    });
+                }
+            }
+            continue;
+        }
+        let Some(describe) = options.describe else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "missing #[state(describe = \"...\")]",
+            ));
+        };
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { Self::#ident { .. } },
+            Fields::Unnamed(_) => quote! { Self::#ident(..) },
+            Fields::Unit => quote! { Self::#ident },
+        };
+        describe_arms.push(quote! {
+            #pattern => #describe.to_string(),
+        });
+        terminal_arms.push(quote! {
+            #pattern => #terminal,
+        });
+        side_effects_arms.push(quote! {
+            #pattern => #side_effects,
+        });
+        idempotent_arms.push(quote! {
+            #pattern => #idempotent,
+        });
+        skip_arms.push(match &options.skip {
+            Some(skip) => quote! { #pattern => Some(#skip), },
+            None => quote! { #pattern => None, },
+        });
+        match &options.next {
+            Some(next) => next_arms.push(quote! { #pattern => (#next).await, }),
+            None if terminal => next_arms.push(quote! { state @ #pattern => Ok(state), }),
+            None => needs_transition = true,
+        }
+    }
+    if needs_transition {
+        next_arms.push(quote_spanned! {name.span()=>
+            state => Self::transition(state).await,
+        });
+    }
+
+    Ok(quote! {
+        #[::async_trait::async_trait]
+        impl #impl_generics #trait_path for #name #type_generics #where_clause {
+            fn label(&self) -> &'static str {
+                match self {
+                    #(#label_arms)*
+                }
+            }
+
+            fn describe(&self) -> String {
+                match self {
+                    #(#describe_arms)*
+                }
+            }
+
+            async fn next(self) -> ::eyre::Result<Self> {
+                match self {
+                    #(#next_arms)*
+                }
+            }
+
+            fn is_terminal(&self) -> bool {
+                match self {
+                    #(#terminal_arms)*
+                }
+            }
+
+            fn has_side_effects(&self) -> bool {
+                match self {
+                    #(#side_effects_arms)*
+                }
+            }
+
+            fn is_idempotent(&self) -> bool {
+                match self {
+                    #(#idempotent_arms)*
+                }
+            }
+
+            fn skip(&self) -> Option<Self> {
+                match self {
+                    #(#skip_arms)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,9 @@
+#[derive(nanuak_state_derive::State)]
+enum MissingDescribe {
+    #[state(describe = "Start")]
+    Start,
+    #[state(terminal)]
+    Done,
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+mod state {
+    #[async_trait::async_trait]
+    pub trait State: Sized + Send {
+        fn label(&self) -> &'static str;
+        fn describe(&self) -> String;
+        async fn next(self) -> eyre::Result<Self>;
+        fn is_terminal(&self) -> bool;
+        fn has_side_effects(&self) -> bool;
+        fn is_idempotent(&self) -> bool;
+        fn skip(&self) -> Option<Self>;
+    }
+}
+
+#[derive(nanuak_state_derive::State)]
+enum MissingTransition {
+    #[state(describe = "Start")]
+    Start,
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(nanuak_state_derive::State)]
+enum NextOnNewtype {
+    #[state(next = Self::choose())]
+    Inner(Box<NextOnNewtype>),
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[derive(nanuak_state_derive::State)]
+struct NotAnEnum {
+    word: String,
+}
+
+fn main() {}
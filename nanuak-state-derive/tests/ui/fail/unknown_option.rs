@@ -0,0 +1,7 @@
+#[derive(nanuak_state_derive::State)]
+enum UnknownOption {
+    #[state(describe = "Start", retry)]
+    Start,
+}
+
+fn main() {}
@@ -0,0 +1,69 @@
+mod state {
+    pub use nanuak_state_derive::State;
+
+    #[async_trait::async_trait]
+    pub trait State: Sized + Send {
+        fn label(&self) -> &'static str;
+        fn describe(&self) -> String;
+        async fn next(self) -> eyre::Result<Self>;
+        fn is_terminal(&self) -> bool;
+        fn has_side_effects(&self) -> bool {
+            false
+        }
+        fn is_idempotent(&self) -> bool {
+            !self.has_side_effects()
+        }
+        fn skip(&self) -> Option<Self> {
+            None
+        }
+    }
+}
+
+use state::State;
+
+#[derive(Debug, Clone, PartialEq, State)]
+enum WordState {
+    #[state(describe = "Ask for a word")]
+    AskForWord,
+    #[state(describe = "Write the word", side_effects, idempotent)]
+    WriteWord { word: String },
+    #[state(describe = "Check the word", side_effects, skip = Self::Done)]
+    CheckWord(String, usize),
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+
+impl WordState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::AskForWord => Ok(Self::WriteWord {
+                word: "kettle".to_string(),
+            }),
+            Self::WriteWord { word } => Ok(Self::CheckWord(word, 0)),
+            Self::CheckWord(..) => Ok(Self::Done),
+            Self::Done => panic!("terminal variants stay where they are without a transition"),
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> eyre::Result<()> {
+    let write = WordState::WriteWord {
+        word: "kettle".to_string(),
+    };
+    assert_eq!(write.label(), "WriteWord");
+    assert_eq!(write.describe(), "Write the word");
+    assert!(write.has_side_effects() && write.is_idempotent());
+    assert_eq!(write.skip(), None);
+
+    let check = WordState::CheckWord("kettle".to_string(), 0);
+    assert!(check.has_side_effects() && !check.is_idempotent());
+    assert_eq!(check.skip(), Some(WordState::Done));
+
+    let mut state = WordState::AskForWord;
+    while !state.is_terminal() {
+        state = state.next().await?;
+    }
+    assert_eq!(state.next().await?, WordState::Done);
+    Ok(())
+}
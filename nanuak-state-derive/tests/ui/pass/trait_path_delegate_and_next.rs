@@ -0,0 +1,93 @@
+//! The trait isn't in scope and lives somewhere other than `crate::state::State`.
+
+mod machine {
+    #[async_trait::async_trait]
+    pub trait Step: Sized + Send {
+        fn label(&self) -> &'static str;
+        fn describe(&self) -> String;
+        async fn next(self) -> eyre::Result<Self>;
+        fn is_terminal(&self) -> bool;
+        fn has_side_effects(&self) -> bool {
+            false
+        }
+        fn is_idempotent(&self) -> bool {
+            !self.has_side_effects()
+        }
+        fn skip(&self) -> Option<Self> {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, nanuak_state_derive::State)]
+#[state(trait_path = crate::machine::Step)]
+enum InnerState {
+    #[state(describe = "Start", side_effects, skip = Self::Done)]
+    Start,
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+
+impl InnerState {
+    async fn transition(self) -> eyre::Result<Self> {
+        Ok(Self::Done)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, nanuak_state_derive::State)]
+#[state(trait_path = crate::machine::Step)]
+enum NestedState {
+    #[state(describe = "Inner")]
+    Inner(InnerState),
+}
+
+#[derive(Debug, Clone, PartialEq, nanuak_state_derive::State)]
+#[state(trait_path = crate::machine::Step, delegate = step_action)]
+enum MenuState {
+    #[state(describe = "Menu", next = Self::choose())]
+    Menu,
+    #[state(skip = Self::Menu)]
+    Action(InnerState),
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+
+impl MenuState {
+    async fn choose() -> eyre::Result<Self> {
+        Ok(Self::Action(InnerState::Start))
+    }
+}
+
+async fn step_action(
+    label: &str,
+    state: InnerState,
+    wrap: fn(InnerState) -> MenuState,
+) -> eyre::Result<MenuState> {
+    assert_eq!(label, "Action");
+    let next = crate::machine::Step::next(state).await?;
+    match crate::machine::Step::is_terminal(&next) {
+        true => Ok(MenuState::Done),
+        false => Ok(wrap(next)),
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> eyre::Result<()> {
+    use crate::machine::Step;
+
+    let nested = NestedState::Inner(InnerState::Start);
+    assert_eq!(nested.describe(), "Inner - Start");
+    assert!(nested.has_side_effects());
+    assert_eq!(nested.skip(), Some(NestedState::Inner(InnerState::Done)));
+    let nested = nested.next().await?;
+    assert!(nested.is_terminal());
+
+    let action = MenuState::Action(InnerState::Start);
+    assert_eq!(action.label(), "Action");
+    assert!(!action.is_terminal());
+    assert_eq!(action.skip(), Some(MenuState::Action(InnerState::Done)));
+    let menu = MenuState::Menu.next().await?;
+    assert_eq!(menu, action);
+    assert_eq!(menu.next().await?, MenuState::Done);
+    Ok(())
+}
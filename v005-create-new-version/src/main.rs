@@ -124,17 +124,16 @@ async fn get_versions(nanuak_dictionary_root_dir: impl AsRef<Path>) -> eyre::Res
     let mut versions = Vec::new();
     while let Some(entry) = children.next_entry().await? {
         let path = entry.path();
-        if path.is_dir() {
-            if is_valid_version_name(
+        if path.is_dir()
+            && is_valid_version_name(
                 &path
                     .file_name()
                     .ok_or_else(|| eyre!("No file name"))?
                     .to_string_lossy(),
             )
             .is_ok()
-            {
-                versions.push(path);
-            }
+        {
+            versions.push(path);
         }
     }
     versions.sort();
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use eyre::bail;
 use eyre::Context;
+use eyre::OptionExt;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
@@ -66,7 +67,7 @@ impl State for CreateNewVersionState {
                 let nanuak_dictionary_root_dir =
                     get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
                 let workspace_cargo_toml_path = nanuak_dictionary_root_dir.join("Cargo.toml");
-                if tokio::fs::try_exists(&workspace_cargo_toml_path).await? == false {
+                if !tokio::fs::try_exists(&workspace_cargo_toml_path).await? {
                     bail!(
                         "Cargo.toml not found at {}",
                         workspace_cargo_toml_path.display()
@@ -80,7 +81,10 @@ impl State for CreateNewVersionState {
             Self::IdentifyNextVersionNumber {
                 workspace_cargo_toml_path,
             } => {
-                let versions = get_versiocns(&nanuak_dictionary_root_dir).await?;
+                let nanuak_dictionary_root_dir = workspace_cargo_toml_path
+                    .parent()
+                    .ok_or_eyre("No parent dir")?;
+                let versions = get_versions(nanuak_dictionary_root_dir).await?;
                 for version in &versions {
                     println!("{}", version.display());
                 }
@@ -1,3 +1,12 @@
+// The create-new-version states and define_word are still todo!() in this version.
+#![allow(
+    unused_variables,
+    unreachable_code,
+    dead_code,
+    async_fn_in_trait,
+    clippy::diverging_sub_expression
+)]
+
 pub mod state;
 pub mod define_word_state;
 pub mod create_new_version_state;
@@ -22,7 +31,7 @@ impl Action {
         let actions = Action::VARIANTS;
         let action = dialoguer::Select::new()
             .with_prompt("Choose an action")
-            .items(&actions)
+            .items(actions)
             .default(0)
             .interact()?;
         Ok(&actions[action])
@@ -1,4 +1,5 @@
-use v025_dictionary::{state::DictionaryApplicationState, Action};
+use v025_dictionary::state::DictionaryApplicationState;
+use v025_dictionary::state::State;
 
 #[tokio::main]
 pub async fn main() -> eyre::Result<()> {
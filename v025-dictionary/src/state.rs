@@ -1,12 +1,9 @@
-use std::path::PathBuf;
-
 use cloud_terrastodon_core_user_input::prelude::Choice;
 use cloud_terrastodon_core_user_input::prelude::FzfArgs;
 use cloud_terrastodon_core_user_input::prelude::pick;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::create_new_version;
 use crate::create_new_version_state::CreateNewVersionState;
 use crate::define_word_state::DefineWordState;
 
@@ -20,7 +17,9 @@ pub enum DictionaryApplicationState {
 }
 const INITIAL_ACTIONS: [DictionaryApplicationState; 2] = [
     DictionaryApplicationState::DefineWord(DefineWordState::PromptingForWordToDefine),
-    DictionaryApplicationState::BeginCreateNewVersion,
+    DictionaryApplicationState::CreateNewVersion(
+        CreateNewVersionState::DetermineWorkspaceCargoTomlPath,
+    ),
 ];
 
 pub trait State {
@@ -35,7 +34,7 @@ impl State for DictionaryApplicationState {
     fn describe(&self) -> &'static str {
         match self {
             Self::JustLaunchedNoArgs => "Start the application",
-            Self::BeginCreateNewVersion => "Create a new version",
+            Self::CreateNewVersion(state) => state.describe(),
             Self::Done => "Done",
             Self::DefineWord(state) => state.describe(),
         }
@@ -64,9 +63,13 @@ impl State for DictionaryApplicationState {
                     Ok(Self::DefineWord(next_state))
                 }
             }
-            Self::BeginCreateNewVersion => {
-                create_new_version().await?;
-                Ok(Self::Done)
+            Self::CreateNewVersion(state) => {
+                let next_state = state.next().await?;
+                if next_state.is_terminal() {
+                    Ok(Self::Done)
+                } else {
+                    Ok(Self::CreateNewVersion(next_state))
+                }
             }
             Self::Done => Ok(Self::Done),
         }
@@ -78,7 +78,7 @@ impl State for CreateNewVersionState {
                 let nanuak_dictionary_root_dir =
                     get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
                 let workspace_cargo_toml_path = nanuak_dictionary_root_dir.join("Cargo.toml");
-                if tokio::fs::try_exists(&workspace_cargo_toml_path).await? == false {
+                if !tokio::fs::try_exists(&workspace_cargo_toml_path).await? {
                     bail!(
                         "Cargo.toml not found at {}",
                         workspace_cargo_toml_path.display()
@@ -4,7 +4,7 @@ pub mod state;
 
 pub async fn define_word(_word: &str) -> eyre::Result<()> {
     tracing::info!("Defining a word");
-    let _definition = todo!("get word definition");
+    todo!("get word definition")
 }
 
 pub async fn prompt_user_for_word() -> eyre::Result<String> {
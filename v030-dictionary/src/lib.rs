@@ -22,7 +22,7 @@ impl Action {
         let actions = Action::VARIANTS;
         let action = dialoguer::Select::new()
             .with_prompt("Choose an action")
-            .items(&actions)
+            .items(actions)
             .default(0)
             .interact()?;
         Ok(&actions[action])
@@ -37,7 +37,7 @@ pub async fn create_new_version() -> eyre::Result<()> {
 
 pub async fn define_word(_word: &str) -> eyre::Result<()> {
     tracing::info!("Defining a word");
-    let _definition = todo!("get word definition");
+    todo!("get word definition")
     // println!("{}: {}", word, definition);
     // Ok(())
 }
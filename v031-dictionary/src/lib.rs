@@ -10,7 +10,7 @@ pub async fn create_new_version() -> eyre::Result<()> {
 
 pub async fn define_word(_word: &str) -> eyre::Result<()> {
     tracing::info!("Defining a word");
-    let _definition = todo!("get word definition");
+    todo!("get word definition")
     // println!("{}: {}", word, definition);
     // Ok(())
 }
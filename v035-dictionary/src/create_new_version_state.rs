@@ -211,7 +211,7 @@ impl State for CreateNewVersionState {
                 template_version_dir,
             } => {
                 let workspace_cargo_toml_path = workspace_dir.join("Cargo.toml");
-                if tokio::fs::try_exists(&workspace_cargo_toml_path).await? == false {
+                if !tokio::fs::try_exists(&workspace_cargo_toml_path).await? {
                     bail!(
                         "Cargo.toml not found at {}",
                         workspace_cargo_toml_path.display()
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use eyre::OptionExt;
+use eyre::bail;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Name of the manifest entry stored at the root of every version archive.
+pub const ARCHIVE_MANIFEST_NAME: &str = "nanuak-archive.json";
+
+/// Name of the directory, relative to the workspace, holding version archives.
+pub const ARCHIVE_DIR_NAME: &str = "archive";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchiveManifest {
+    pub versions: Vec<ArchivedVersion>,
+}
+
+/// Remembers how a version was wired into the workspace so a restore can put it back.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchivedVersion {
+    pub name: String,
+    pub workspace_member: bool,
+    pub workspace_dependency_path: Option<String>,
+}
+
+pub fn get_archive_dir(workspace_dir: impl AsRef<Path>) -> PathBuf {
+    workspace_dir.as_ref().join(ARCHIVE_DIR_NAME)
+}
+
+pub fn get_archive_file_name(version_names: &[String]) -> eyre::Result<String> {
+    let first = version_names.first().ok_or_eyre("No versions to archive")?;
+    let last = version_names.last().ok_or_eyre("No versions to archive")?;
+    if first == last {
+        Ok(format!("{first}.tar.zst"))
+    } else {
+        Ok(format!("{first}--{last}.tar.zst"))
+    }
+}
+
+pub async fn get_archives(workspace_dir: impl AsRef<Path>) -> eyre::Result<Vec<PathBuf>> {
+    let archive_dir = get_archive_dir(workspace_dir);
+    if !tokio::fs::try_exists(&archive_dir).await? {
+        return Ok(Vec::new());
+    }
+    let mut children = tokio::fs::read_dir(&archive_dir).await?;
+    let mut archives = Vec::new();
+    while let Some(entry) = children.next_entry().await? {
+        let path = entry.path();
+        if path.is_file() && path.to_string_lossy().ends_with(".tar.zst") {
+            archives.push(path);
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+/// Write the given version directories and the manifest into a single `.tar.zst` file.
+pub async fn write_archive(
+    workspace_dir: PathBuf,
+    manifest: ArchiveManifest,
+    archive_path: PathBuf,
+) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        if let Some(parent) = archive_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create_new(&archive_path)
+            .context(format!("Creating archive {}", archive_path.display()))?;
+        let encoder = zstd::Encoder::new(file, 19)?;
+        let mut builder = tar::Builder::new(encoder);
+
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, ARCHIVE_MANIFEST_NAME, manifest_json.as_slice())?;
+
+        for version in &manifest.versions {
+            let version_dir = workspace_dir.join(&version.name);
+            append_version_dir(&mut builder, Path::new(&version.name), &version_dir)
+                .context(format!("Adding {} to archive", version_dir.display()))?;
+        }
+        builder.into_inner()?.finish()?;
+        eyre::Ok(())
+    })
+    .await?
+}
+
+/// Like [`tar::Builder::append_dir_all`] but leaves out `target` build output.
+fn append_version_dir<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    archive_path: &Path,
+    dir: &Path,
+) -> eyre::Result<()> {
+    builder.append_dir(archive_path, dir)?;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name() == "target" {
+            continue;
+        }
+        let entry_archive_path = archive_path.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            append_version_dir(builder, &entry_archive_path, &entry.path())?;
+        } else {
+            builder.append_path_with_name(entry.path(), &entry_archive_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The files under `dir`, leaving out `target` like [`append_version_dir`] does.
+fn get_version_files(archive_path: &Path, dir: &Path) -> eyre::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut rtn = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name() == "target" {
+            continue;
+        }
+        let entry_archive_path = archive_path.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            rtn.extend(get_version_files(&entry_archive_path, &entry.path())?);
+        } else {
+            rtn.push((entry_archive_path, entry.path()));
+        }
+    }
+    Ok(rtn)
+}
+
+/// Re-read an archive and check it holds every file of its versions byte for byte,
+/// so the version dirs can be removed without losing anything.
+pub async fn verify_archive(
+    archive_path: PathBuf,
+    workspace_dir: PathBuf,
+    manifest: ArchiveManifest,
+) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&archive_path)
+            .context(format!("Opening archive {}", archive_path.display()))?;
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+        let mut archived = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?.to_path_buf();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            archived.insert(path, contents);
+        }
+        if archived.get(Path::new(ARCHIVE_MANIFEST_NAME))
+            != Some(&serde_json::to_vec_pretty(&manifest)?)
+        {
+            bail!(
+                "The manifest in {} does not match the versions being archived",
+                archive_path.display()
+            );
+        }
+        for version in &manifest.versions {
+            let version_dir = workspace_dir.join(&version.name);
+            for (entry_archive_path, path) in
+                get_version_files(Path::new(&version.name), &version_dir)?
+            {
+                let Some(contents) = archived.get(&entry_archive_path) else {
+                    bail!(
+                        "{} is missing from {}",
+                        entry_archive_path.display(),
+                        archive_path.display()
+                    );
+                };
+                if *contents
+                    != std::fs::read(&path).context(format!("Reading {}", path.display()))?
+                {
+                    bail!(
+                        "{} differs from the copy in {}",
+                        path.display(),
+                        archive_path.display()
+                    );
+                }
+            }
+        }
+        eyre::Ok(())
+    })
+    .await?
+}
+
+pub async fn read_archive_manifest(archive_path: PathBuf) -> eyre::Result<ArchiveManifest> {
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&archive_path)
+            .context(format!("Opening archive {}", archive_path.display()))?;
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.path()?.as_os_str() == ARCHIVE_MANIFEST_NAME {
+                let mut manifest_json = String::new();
+                entry.read_to_string(&mut manifest_json)?;
+                let manifest = serde_json::from_str(&manifest_json).context(format!(
+                    "Parsing {} from {}",
+                    ARCHIVE_MANIFEST_NAME,
+                    archive_path.display()
+                ))?;
+                return Ok(manifest);
+            }
+        }
+        bail!(
+            "No {} found in {}",
+            ARCHIVE_MANIFEST_NAME,
+            archive_path.display()
+        )
+    })
+    .await?
+}
+
+/// Unpack the version directories of an archive into the workspace, skipping the manifest.
+pub async fn extract_archive(archive_path: PathBuf, workspace_dir: PathBuf) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&archive_path)
+            .context(format!("Opening archive {}", archive_path.display()))?;
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.path()?.as_os_str() == ARCHIVE_MANIFEST_NAME {
+                continue;
+            }
+            entry.unpack_in(&workspace_dir)?;
+        }
+        eyre::Ok(())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn write_test_version(workspace_dir: &Path) -> eyre::Result<ArchiveManifest> {
+        let version_dir = workspace_dir.join("v001-test");
+        tokio::fs::create_dir_all(version_dir.join("src")).await?;
+        tokio::fs::create_dir_all(version_dir.join("target")).await?;
+        tokio::fs::write(
+            version_dir.join("Cargo.toml"),
+            "[package]\nname = \"v001-test\"\n",
+        )
+        .await?;
+        tokio::fs::write(version_dir.join("src/main.rs"), "fn main() {}\n").await?;
+        tokio::fs::write(version_dir.join("target/build-output"), "not archived").await?;
+        Ok(ArchiveManifest {
+            versions: vec![ArchivedVersion {
+                name: "v001-test".to_string(),
+                workspace_member: true,
+                workspace_dependency_path: Some("v001-test".to_string()),
+            }],
+        })
+    }
+
+    #[tokio::test]
+    async fn archive_round_trips_version_dirs() -> eyre::Result<()> {
+        let workspace_dir = tempfile::tempdir()?;
+        let workspace_dir = workspace_dir.path().to_path_buf();
+        let manifest = write_test_version(&workspace_dir).await?;
+        let archive_path = get_archive_dir(&workspace_dir).join("v001-test.tar.zst");
+        write_archive(
+            workspace_dir.clone(),
+            manifest.clone(),
+            archive_path.clone(),
+        )
+        .await?;
+        verify_archive(
+            archive_path.clone(),
+            workspace_dir.clone(),
+            manifest.clone(),
+        )
+        .await?;
+        assert_eq!(read_archive_manifest(archive_path.clone()).await?, manifest);
+
+        tokio::fs::remove_dir_all(workspace_dir.join("v001-test")).await?;
+        extract_archive(archive_path.clone(), workspace_dir.clone()).await?;
+        assert_eq!(
+            tokio::fs::read_to_string(workspace_dir.join("v001-test/src/main.rs")).await?,
+            "fn main() {}\n"
+        );
+        assert!(!tokio::fs::try_exists(workspace_dir.join("v001-test/target")).await?);
+        assert_eq!(get_archives(&workspace_dir).await?, vec![archive_path]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_archive_rejects_files_changed_since_writing() -> eyre::Result<()> {
+        let workspace_dir = tempfile::tempdir()?;
+        let workspace_dir = workspace_dir.path().to_path_buf();
+        let manifest = write_test_version(&workspace_dir).await?;
+        let archive_path = get_archive_dir(&workspace_dir).join("v001-test.tar.zst");
+        write_archive(
+            workspace_dir.clone(),
+            manifest.clone(),
+            archive_path.clone(),
+        )
+        .await?;
+
+        tokio::fs::write(workspace_dir.join("v001-test/src/lib.rs"), "").await?;
+        assert!(
+            verify_archive(
+                archive_path.clone(),
+                workspace_dir.clone(),
+                manifest.clone()
+            )
+            .await
+            .is_err()
+        );
+        tokio::fs::remove_file(workspace_dir.join("v001-test/src/lib.rs")).await?;
+        tokio::fs::write(
+            workspace_dir.join("v001-test/src/main.rs"),
+            "fn main() { todo!() }\n",
+        )
+        .await?;
+        assert!(
+            verify_archive(archive_path, workspace_dir, manifest)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
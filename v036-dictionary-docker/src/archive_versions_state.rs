@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+
+use eyre::OptionExt;
+use eyre::bail;
+use itertools::Itertools;
+use nanuak_core::cargo_toml;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::archive::ArchiveManifest;
+use crate::archive::ArchivedVersion;
+use crate::archive::get_archive_dir;
+use crate::archive::get_archive_file_name;
+use crate::archive::verify_archive;
+use crate::archive::write_archive;
+use crate::state::State;
+use crate::workspace::get_path_dependents;
+use crate::workspace::read_workspace_cargo_toml;
+use crate::workspace::write_workspace_cargo_toml;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum ArchiveVersionsState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose versions to archive")]
+    ChooseVersionsToArchive { workspace_dir: PathBuf },
+    #[state(describe = "Check for live dependents")]
+    CheckForLiveDependents {
+        workspace_dir: PathBuf,
+        version_names: Vec<String>,
+    },
+    #[state(describe = "Write archive", side_effects)]
+    WriteArchive {
+        workspace_dir: PathBuf,
+        version_names: Vec<String>,
+    },
+    #[state(describe = "Update workspace Cargo.toml", side_effects, idempotent)]
+    UpdateWorkspaceCargoToml {
+        workspace_dir: PathBuf,
+        manifest: ArchiveManifest,
+        archive_path: PathBuf,
+    },
+    #[state(describe = "Remove version dirs", side_effects)]
+    RemoveVersionDirs {
+        workspace_dir: PathBuf,
+        manifest: ArchiveManifest,
+        archive_path: PathBuf,
+    },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl ArchiveVersionsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseVersionsToArchive { workspace_dir })
+            }
+            Self::ChooseVersionsToArchive { workspace_dir } => {
+                let version_names = get_versions(&workspace_dir)
+                    .await?
+                    .into_iter()
+                    .map(|version| {
+                        version
+                            .file_name()
+                            .map(|x| x.to_string_lossy().to_string())
+                            .ok_or_eyre(format!("No file name for {version:?}"))
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                let chosen = answer_opt("Choose the versions to archive", |prompt| {
+                    Ok(dialoguer::MultiSelect::new()
+                        .with_prompt(prompt)
+                        .items(&version_names)
+                        .interact_opt()?)
+                })?;
+                if chosen.is_empty() {
+                    bail!("No versions chosen to archive");
+                }
+                let version_names = chosen
+                    .into_iter()
+                    .map(|i| version_names[i].clone())
+                    .collect_vec();
+                Ok(Self::CheckForLiveDependents {
+                    workspace_dir,
+                    version_names,
+                })
+            }
+            Self::CheckForLiveDependents {
+                workspace_dir,
+                version_names,
+            } => {
+                info!("Ensure no remaining workspace member depends on the chosen versions");
+                let dependents = get_path_dependents(&workspace_dir, &version_names).await?;
+                if !dependents.is_empty() {
+                    bail!(
+                        "Refusing to archive versions that live versions still depend on by path:\n{}",
+                        dependents
+                            .iter()
+                            .map(|x| format!("  {} depends on {}", x.dependent, x.dependency))
+                            .join("\n")
+                    );
+                }
+                Ok(Self::WriteArchive {
+                    workspace_dir,
+                    version_names,
+                })
+            }
+            Self::WriteArchive {
+                workspace_dir,
+                version_names,
+            } => {
+                let workspace_cargo_toml = read_workspace_cargo_toml(&workspace_dir).await?;
+                let workspace = workspace_cargo_toml
+                    .workspace
+                    .as_ref()
+                    .ok_or_eyre("No workspace")?;
+                let manifest = ArchiveManifest {
+                    versions: version_names
+                        .iter()
+                        .map(|name| ArchivedVersion {
+                            name: name.clone(),
+                            workspace_member: workspace.members.contains(name),
+                            workspace_dependency_path: match workspace.dependencies.0.get(name) {
+                                Some(cargo_toml::Dependency::Path { path, .. }) => {
+                                    Some(path.clone())
+                                }
+                                _ => None,
+                            },
+                        })
+                        .collect(),
+                };
+                let archive_path =
+                    get_archive_dir(&workspace_dir).join(get_archive_file_name(&version_names)?);
+                info!(
+                    "Writing {} versions to {}",
+                    version_names.len(),
+                    archive_path.display()
+                );
+                write_archive(
+                    workspace_dir.clone(),
+                    manifest.clone(),
+                    archive_path.clone(),
+                )
+                .await?;
+                info!(
+                    "Verify {} before touching the workspace",
+                    archive_path.display()
+                );
+                verify_archive(
+                    archive_path.clone(),
+                    workspace_dir.clone(),
+                    manifest.clone(),
+                )
+                .await?;
+                Ok(Self::UpdateWorkspaceCargoToml {
+                    workspace_dir,
+                    manifest,
+                    archive_path,
+                })
+            }
+            Self::UpdateWorkspaceCargoToml {
+                workspace_dir,
+                manifest,
+                archive_path,
+            } => {
+                info!("Remove the archived versions from the workspace Cargo.toml");
+                let mut workspace_cargo_toml = read_workspace_cargo_toml(&workspace_dir).await?;
+                let workspace = workspace_cargo_toml
+                    .workspace
+                    .as_mut()
+                    .ok_or_eyre("No workspace")?;
+                for version in &manifest.versions {
+                    workspace.members.retain(|member| member != &version.name);
+                    workspace.dependencies.0.remove(&version.name);
+                }
+                write_workspace_cargo_toml(&workspace_dir, &workspace_cargo_toml).await?;
+                Ok(Self::RemoveVersionDirs {
+                    workspace_dir,
+                    manifest,
+                    archive_path,
+                })
+            }
+            Self::RemoveVersionDirs {
+                workspace_dir,
+                manifest,
+                archive_path: _,
+            } => {
+                for version in &manifest.versions {
+                    let version_dir = workspace_dir.join(&version.name);
+                    info!("Removing {}", version_dir.display());
+                    tokio::fs::remove_dir_all(&version_dir).await?;
+                }
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::path::Path;
+
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncReadExt;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::DictionaryEntry;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum BatchOutputFormat {
+    JsonLines,
+    Table,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchLookupStatus {
+    Found,
+    Missing,
+    /// More than one definition matched, so a glossary should say which sense it means.
+    Ambiguous,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchLookup {
+    pub word: String,
+    pub status: BatchLookupStatus,
+    pub entries: Vec<DictionaryEntry>,
+}
+
+impl BatchLookupStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Found => "found",
+            Self::Missing => "missing",
+            Self::Ambiguous => "ambiguous",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct BatchSummary {
+    pub found: usize,
+    pub missing: Vec<String>,
+    pub ambiguous: Vec<String>,
+}
+
+impl BatchSummary {
+    pub fn new(lookups: &[BatchLookup]) -> Self {
+        let mut rtn = Self::default();
+        for lookup in lookups {
+            match lookup.status {
+                BatchLookupStatus::Found => rtn.found += 1,
+                BatchLookupStatus::Missing => rtn.missing.push(lookup.word.clone()),
+                BatchLookupStatus::Ambiguous => rtn.ambiguous.push(lookup.word.clone()),
+            }
+        }
+        rtn
+    }
+
+    pub fn render(&self) -> String {
+        let mut rtn = format!(
+            "{} found, {} missing, {} ambiguous",
+            self.found,
+            self.missing.len(),
+            self.ambiguous.len()
+        );
+        if !self.missing.is_empty() {
+            rtn.push_str(&format!("\nMissing: {}", self.missing.join(", ")));
+        }
+        if !self.ambiguous.is_empty() {
+            rtn.push_str(&format!("\nAmbiguous: {}", self.ambiguous.join(", ")));
+        }
+        rtn
+    }
+}
+
+/// Newline separated words from `input`, or stdin when it is `None` or `-`, skipping blank lines.
+pub async fn read_batch_words(input: Option<&Path>) -> eyre::Result<Vec<String>> {
+    let contents = match input {
+        Some(path) if path != Path::new("-") => tokio::fs::read_to_string(path)
+            .await
+            .context(format!("Reading words from {}", path.display()))?,
+        _ => {
+            let mut contents = String::new();
+            tokio::io::stdin()
+                .read_to_string(&mut contents)
+                .await
+                .context("Reading words from stdin")?;
+            contents
+        }
+    };
+    Ok(contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+pub fn lookup_batch(dictionary: &Dictionary, words: &[String]) -> Vec<BatchLookup> {
+    words
+        .iter()
+        .map(|word| {
+            let entries: Vec<DictionaryEntry> =
+                dictionary.define(word).into_iter().cloned().collect();
+            let status = match entries.len() {
+                0 => BatchLookupStatus::Missing,
+                1 => BatchLookupStatus::Found,
+                _ => BatchLookupStatus::Ambiguous,
+            };
+            BatchLookup {
+                word: word.clone(),
+                status,
+                entries,
+            }
+        })
+        .collect()
+}
+
+pub fn render_json_lines(lookups: &[BatchLookup]) -> eyre::Result<String> {
+    let mut rtn = String::new();
+    for lookup in lookups {
+        rtn.push_str(&serde_json::to_string(lookup)?);
+        rtn.push('\n');
+    }
+    Ok(rtn)
+}
+
+/// One row per definition, with missing words getting a row of their own.
+pub fn render_table(lookups: &[BatchLookup]) -> String {
+    let mut rows = vec![[
+        "WORD".to_string(),
+        "STATUS".to_string(),
+        "SOURCE".to_string(),
+        "DEFINITION".to_string(),
+    ]];
+    for lookup in lookups {
+        let status = lookup.status.as_str().to_string();
+        if lookup.entries.is_empty() {
+            rows.push([
+                lookup.word.clone(),
+                status.clone(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        for entry in &lookup.entries {
+            rows.push([
+                lookup.word.clone(),
+                status.clone(),
+                entry.source.clone(),
+                entry.definition.clone(),
+            ]);
+        }
+    }
+    let widths: Vec<usize> = (0..3)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut rtn = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (column, width) in widths.iter().enumerate() {
+            line.push_str(&format!("{:<width$}  ", row[column], width = width));
+        }
+        line.push_str(&row[3]);
+        rtn.push_str(line.trim_end());
+        rtn.push('\n');
+    }
+    rtn
+}
+
+/// Look up every word from `input`, print the results to stdout and the summary to stderr.
+pub async fn run_batch_define(
+    dictionary: &Dictionary,
+    input: Option<&Path>,
+    format: BatchOutputFormat,
+) -> eyre::Result<BatchSummary> {
+    let words = read_batch_words(input).await?;
+    let lookups = lookup_batch(dictionary, &words);
+    match format {
+        BatchOutputFormat::JsonLines => print!("{}", render_json_lines(&lookups)?),
+        BatchOutputFormat::Table => print!("{}", render_table(&lookups)),
+    }
+    let summary = BatchSummary::new(&lookups);
+    eprintln!("{}", summary.render());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_dictionary() -> Dictionary {
+        let entry = |word: &str, definition: &str, source: &str| DictionaryEntry {
+            word: word.to_string(),
+            definition: definition.to_string(),
+            source: source.to_string(),
+        };
+        Dictionary::new(vec![
+            entry("kettle", "A pot for boiling water", "wiktionary"),
+            entry("bank", "The side of a river", "wiktionary"),
+            entry("bank", "A place that keeps money", "glossary"),
+        ])
+    }
+
+    fn get_lookups() -> Vec<BatchLookup> {
+        let words = ["Kettle", "bank", "teapot", "spoon"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        lookup_batch(&get_dictionary(), &words)
+    }
+
+    #[test]
+    fn sorts_words_into_found_missing_and_ambiguous() {
+        let lookups = get_lookups();
+        assert_eq!(
+            lookups.iter().map(|x| x.status).collect::<Vec<_>>(),
+            vec![
+                BatchLookupStatus::Found,
+                BatchLookupStatus::Ambiguous,
+                BatchLookupStatus::Missing,
+                BatchLookupStatus::Missing,
+            ]
+        );
+        assert_eq!(lookups[0].word, "Kettle", "words are kept as given");
+        assert_eq!(lookups[1].entries.len(), 2);
+
+        let summary = BatchSummary::new(&lookups);
+        assert_eq!(
+            summary,
+            BatchSummary {
+                found: 1,
+                missing: vec!["teapot".to_string(), "spoon".to_string()],
+                ambiguous: vec!["bank".to_string()],
+            }
+        );
+        assert_eq!(
+            summary.render(),
+            "1 found, 2 missing, 1 ambiguous\nMissing: teapot, spoon\nAmbiguous: bank"
+        );
+        assert_eq!(
+            BatchSummary::new(&[]).render(),
+            "0 found, 0 missing, 0 ambiguous"
+        );
+    }
+
+    #[test]
+    fn renders_one_json_object_per_word() -> eyre::Result<()> {
+        let lookups = get_lookups();
+        let json_lines = render_json_lines(&lookups)?;
+        let lines = json_lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), lookups.len());
+        assert!(json_lines.ends_with('\n'));
+        let first: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(
+            first,
+            serde_json::json!({
+                "word": "Kettle",
+                "status": "found",
+                "entries": [{
+                    "word": "kettle",
+                    "definition": "A pot for boiling water",
+                    "source": "wiktionary",
+                }],
+            })
+        );
+        let missing: BatchLookup = serde_json::from_str(lines[2])?;
+        assert_eq!(missing.status, BatchLookupStatus::Missing);
+        assert!(missing.entries.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn renders_a_row_per_definition() {
+        assert_eq!(
+            render_table(&get_lookups()),
+            "\
+WORD    STATUS     SOURCE      DEFINITION
+Kettle  found      wiktionary  A pot for boiling water
+bank    ambiguous  wiktionary  The side of a river
+bank    ambiguous  glossary    A place that keeps money
+teapot  missing
+spoon   missing
+"
+        );
+    }
+}
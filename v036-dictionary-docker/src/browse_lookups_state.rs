@@ -0,0 +1,129 @@
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::bail;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::define_word;
+use crate::lookup_history::Favourites;
+use crate::lookup_history::get_latest_lookups;
+use crate::lookup_history::read_history;
+use crate::prompt::pick_answer;
+use crate::state::State;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum BrowseLookupsState {
+    #[default]
+    #[state(describe = "Browse lookup history")]
+    PickFromHistory,
+    #[state(describe = "Browse favourites")]
+    PickFromFavourites,
+    #[state(describe = "Choose what to do with the word")]
+    ChooseWordAction { word: String },
+    #[state(describe = "Define the word again", side_effects, idempotent)]
+    RedefineWord { word: String },
+    #[state(describe = "Star or unstar the word", side_effects)]
+    ToggleFavourite { word: String },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl BrowseLookupsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::PickFromHistory => {
+                let history = read_history().await?;
+                let favourites = Favourites::read().await?;
+                let choices: Vec<Choice<String>> = get_latest_lookups(&history)
+                    .into_iter()
+                    .map(|record| Choice {
+                        key: format!(
+                            "{}  {}{}{}",
+                            record.looked_up_at.format("%Y-%m-%d %H:%M"),
+                            if favourites.contains(&record.word) {
+                                "★ "
+                            } else {
+                                ""
+                            },
+                            record.word,
+                            if record.found { "" } else { " (not found)" }
+                        ),
+                        value: record.word.clone(),
+                    })
+                    .collect();
+                if choices.is_empty() {
+                    bail!("No lookups yet, define a word first");
+                }
+                let chosen = pick_answer(FzfArgs {
+                    choices,
+                    header: Some("Choose a word from your history".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::ChooseWordAction { word: chosen.value })
+            }
+            Self::PickFromFavourites => {
+                let favourites = Favourites::read().await?;
+                let choices: Vec<Choice<String>> = favourites
+                    .favourites
+                    .iter()
+                    .rev()
+                    .map(|favourite| Choice {
+                        key: format!(
+                            "{}  {}",
+                            favourite.starred_at.format("%Y-%m-%d %H:%M"),
+                            favourite.word
+                        ),
+                        value: favourite.word.clone(),
+                    })
+                    .collect();
+                if choices.is_empty() {
+                    bail!("No favourites yet, star a word from your history first");
+                }
+                let chosen = pick_answer(FzfArgs {
+                    choices,
+                    header: Some("Choose a favourite word".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::ChooseWordAction { word: chosen.value })
+            }
+            Self::ChooseWordAction { word } => {
+                let favourites = Favourites::read().await?;
+                let toggle = if favourites.contains(&word) {
+                    "Remove from favourites"
+                } else {
+                    "Star as a favourite"
+                };
+                let chosen = pick_answer(FzfArgs {
+                    choices: vec![
+                        Choice {
+                            key: "Define again".to_string(),
+                            value: Self::RedefineWord { word: word.clone() },
+                        },
+                        Choice {
+                            key: toggle.to_string(),
+                            value: Self::ToggleFavourite { word: word.clone() },
+                        },
+                    ],
+                    header: Some(format!("What should we do with {word}?")),
+                    prompt: None,
+                })?;
+                Ok(chosen.value)
+            }
+            Self::RedefineWord { word } => {
+                define_word(&word).await?;
+                Ok(Self::Done)
+            }
+            Self::ToggleFavourite { word } => {
+                let mut favourites = Favourites::read().await?;
+                if favourites.toggle(&word) {
+                    info!("Starred {}", word);
+                } else {
+                    info!("Unstarred {}", word);
+                }
+                favourites.write().await?;
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::OptionExt;
+use itertools::Itertools;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::cargo_diagnostics::build_version_for_diagnostics;
+use crate::cargo_diagnostics::read_diagnostics_report;
+use crate::cargo_diagnostics::write_diagnostics_report;
+use crate::prompt::pick_answer;
+use crate::state::State;
+use crate::workspace::copy_version_as_unregistered_version;
+use crate::workspace::prompt_unregistered_version_name;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum CaptureDiagnosticsState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose version to build")]
+    ChooseVersion { workspace_dir: PathBuf },
+    #[state(
+        describe = "Build version and capture diagnostics",
+        side_effects,
+        idempotent
+    )]
+    BuildVersion {
+        workspace_dir: PathBuf,
+        version_name: String,
+    },
+    #[state(describe = "Summarize diagnostics")]
+    SummarizeDiagnostics {
+        workspace_dir: PathBuf,
+        report_path: PathBuf,
+    },
+    #[state(describe = "Choose next version name", skip = Self::Done)]
+    ChooseNextVersionName {
+        workspace_dir: PathBuf,
+        report_path: PathBuf,
+    },
+    #[state(describe = "Seed next version from diagnostics", side_effects, skip = Self::Done)]
+    SeedNextVersion {
+        workspace_dir: PathBuf,
+        report_path: PathBuf,
+        next_version_name: String,
+    },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl CaptureDiagnosticsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseVersion { workspace_dir })
+            }
+            Self::ChooseVersion { workspace_dir } => {
+                let mut choices = get_versions(&workspace_dir)
+                    .await?
+                    .into_iter()
+                    .filter(|version| version.join("Cargo.toml").exists())
+                    .map(|version| Choice {
+                        key: version.display().to_string(),
+                        value: version,
+                    })
+                    .collect_vec();
+                choices.reverse();
+                let chosen = pick_answer(FzfArgs {
+                    choices,
+                    header: Some("Choose a version to build".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::BuildVersion {
+                    workspace_dir,
+                    version_name: chosen
+                        .value
+                        .file_name()
+                        .ok_or_eyre(format!("No file name for {:?}", chosen.value))?
+                        .to_string_lossy()
+                        .to_string(),
+                })
+            }
+            Self::BuildVersion {
+                workspace_dir,
+                version_name,
+            } => {
+                let report = build_version_for_diagnostics(&workspace_dir, &version_name).await?;
+                let report_path = write_diagnostics_report(&workspace_dir, &report).await?;
+                info!(
+                    "Stored {} diagnostics in {}",
+                    report.diagnostics.len(),
+                    report_path.display()
+                );
+                Ok(Self::SummarizeDiagnostics {
+                    workspace_dir,
+                    report_path,
+                })
+            }
+            Self::SummarizeDiagnostics {
+                workspace_dir,
+                report_path,
+            } => {
+                let report = read_diagnostics_report(&report_path).await?;
+                println!("{}", report.summarize());
+                if report.errors().next().is_none() {
+                    return Ok(Self::Done);
+                }
+                let seed = answer_opt(
+                    &format!(
+                        "Seed a next version from {} with these errors as TODOs?",
+                        report.version_name
+                    ),
+                    |prompt| {
+                        Ok(dialoguer::Confirm::new()
+                            .with_prompt(prompt)
+                            .interact_opt()?)
+                    },
+                )?;
+                if seed {
+                    Ok(Self::ChooseNextVersionName {
+                        workspace_dir,
+                        report_path,
+                    })
+                } else {
+                    Ok(Self::Done)
+                }
+            }
+            Self::ChooseNextVersionName {
+                workspace_dir,
+                report_path,
+            } => {
+                let report = read_diagnostics_report(&report_path).await?;
+                let next_version_name =
+                    prompt_unregistered_version_name(&workspace_dir, &report.version_name).await?;
+                Ok(Self::SeedNextVersion {
+                    workspace_dir,
+                    report_path,
+                    next_version_name,
+                })
+            }
+            Self::SeedNextVersion {
+                workspace_dir,
+                report_path,
+                next_version_name,
+            } => {
+                let report = read_diagnostics_report(&report_path).await?;
+                let next_version_dir = copy_version_as_unregistered_version(
+                    &workspace_dir,
+                    &report.version_name,
+                    &next_version_name,
+                )
+                .await?;
+
+                info!("Pre-fill the next steps with the errors to fix");
+                let next_steps_path = next_version_dir.join("next steps.md");
+                let mut next_steps = if tokio::fs::try_exists(&next_steps_path).await? {
+                    tokio::fs::read_to_string(&next_steps_path).await?
+                } else {
+                    String::new()
+                };
+                if !next_steps.is_empty() && !next_steps.ends_with('\n') {
+                    next_steps.push('\n');
+                }
+                next_steps.push_str(&report.to_next_steps_todo());
+                tokio::fs::write(&next_steps_path, next_steps).await?;
+
+                info!(
+                    "Seeded {} but left it out of the workspace members until it builds",
+                    next_version_dir.display()
+                );
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
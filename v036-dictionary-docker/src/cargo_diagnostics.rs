@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use itertools::Itertools;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::workspace::copy_version_dir;
+use crate::workspace::pin_path_dependencies;
+use crate::workspace::read_workspace_cargo_toml;
+
+/// A single compiler message from cargo's `--message-format=json` output.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CargoDiagnostic {
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub rendered: Option<String>,
+}
+
+impl CargoDiagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level == "error"
+    }
+
+    pub fn location(&self) -> String {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => format!("{file}:{line}"),
+            (Some(file), None) => file.clone(),
+            _ => "<unknown>".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CargoRun {
+    pub success: bool,
+    pub diagnostics: Vec<CargoDiagnostic>,
+}
+
+impl CargoRun {
+    /// Drop diagnostics from files outside `dir`, relative to where cargo ran, like those of
+    /// path dependencies. Diagnostics without a file, like manifest errors, are kept.
+    pub fn retain_diagnostics_in_dir(&mut self, dir: &str) {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        self.diagnostics
+            .retain(|x| x.file.as_ref().is_none_or(|file| file.starts_with(&prefix)));
+    }
+}
+
+/// Pull the compiler messages out of cargo's JSON lines, ignoring artifacts and anything unparseable.
+pub fn parse_cargo_messages(stdout: &str) -> Vec<CargoDiagnostic> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|x| x["reason"] == "compiler-message")
+        .filter(|x| {
+            !x["message"]["message"]
+                .as_str()
+                .is_some_and(|message| message.starts_with("aborting due to"))
+        })
+        .map(|x| {
+            let message = &x["message"];
+            let primary_span = message["spans"]
+                .as_array()
+                .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true));
+            CargoDiagnostic {
+                level: message["level"].as_str().unwrap_or_default().to_string(),
+                code: message["code"]["code"].as_str().map(|x| x.to_string()),
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+                file: primary_span
+                    .and_then(|span| span["file_name"].as_str())
+                    .map(|x| x.to_string()),
+                line: primary_span
+                    .and_then(|span| span["line_start"].as_u64())
+                    .map(|x| x as usize),
+                rendered: message["rendered"].as_str().map(|x| x.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Run a cargo subcommand in `workspace_dir` with JSON diagnostics, e.g. `["check", "-p", "v037"]`.
+pub async fn run_cargo_with_diagnostics(
+    workspace_dir: impl AsRef<Path>,
+    args: &[&str],
+) -> eyre::Result<CargoRun> {
+    info!("Running cargo {} --message-format=json", args.join(" "));
+    let output = tokio::process::Command::new("cargo")
+        .args(args)
+        .arg("--message-format=json")
+        .current_dir(workspace_dir.as_ref())
+        .output()
+        .await
+        .context(format!("Running cargo {}", args.join(" ")))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut diagnostics = parse_cargo_messages(&stdout);
+    if !output.status.success() && !diagnostics.iter().any(|x| x.is_error()) {
+        // Failures before compilation, like a bad manifest, only show up on stderr
+        diagnostics.push(CargoDiagnostic {
+            level: "error".to_string(),
+            code: None,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            file: None,
+            line: None,
+            rendered: None,
+        });
+    }
+    Ok(CargoRun {
+        success: output.status.success(),
+        diagnostics,
+    })
+}
+
+/// A count of errors and warnings followed by one line per error.
+pub fn summarize_diagnostics(diagnostics: &[CargoDiagnostic]) -> String {
+    let errors = diagnostics.iter().filter(|x| x.is_error()).collect_vec();
+    let warning_count = diagnostics.iter().filter(|x| x.level == "warning").count();
+    let mut rtn = format!("{} errors, {} warnings", errors.len(), warning_count);
+    for error in errors {
+        rtn.push_str(&format!(
+            "\n  {} [{}] {}",
+            error.location(),
+            error.code.as_deref().unwrap_or("-"),
+            error.message
+        ));
+    }
+    rtn
+}
+
+/// The diagnostics captured from building one version, stored as JSON under `diagnostics/`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiagnosticsReport {
+    pub version_name: String,
+    pub success: bool,
+    /// File paths are relative to the version directory.
+    pub diagnostics: Vec<CargoDiagnostic>,
+}
+
+impl DiagnosticsReport {
+    pub fn errors(&self) -> impl Iterator<Item = &CargoDiagnostic> {
+        self.diagnostics.iter().filter(|x| x.is_error())
+    }
+
+    pub fn errors_by_code(&self) -> BTreeMap<String, Vec<&CargoDiagnostic>> {
+        let mut rtn: BTreeMap<String, Vec<&CargoDiagnostic>> = BTreeMap::new();
+        for error in self.errors() {
+            rtn.entry(error.code.clone().unwrap_or_else(|| "-".to_string()))
+                .or_default()
+                .push(error);
+        }
+        rtn
+    }
+
+    pub fn errors_by_file(&self) -> BTreeMap<String, Vec<&CargoDiagnostic>> {
+        let mut rtn: BTreeMap<String, Vec<&CargoDiagnostic>> = BTreeMap::new();
+        for error in self.errors() {
+            rtn.entry(
+                error
+                    .file
+                    .clone()
+                    .unwrap_or_else(|| "<unknown>".to_string()),
+            )
+            .or_default()
+            .push(error);
+        }
+        rtn
+    }
+
+    pub fn summarize(&self) -> String {
+        let mut rtn = format!(
+            "{}: {}",
+            self.version_name,
+            summarize_diagnostics(&self.diagnostics)
+        );
+        rtn.push_str("\nBy code:");
+        for (code, errors) in self.errors_by_code() {
+            rtn.push_str(&format!("\n  {code}: {}", errors.len()));
+        }
+        rtn.push_str("\nBy file:");
+        for (file, errors) in self.errors_by_file() {
+            rtn.push_str(&format!("\n  {file}: {}", errors.len()));
+        }
+        rtn
+    }
+
+    /// A markdown checklist of the errors, grouped by file, for a `next steps.md`.
+    pub fn to_next_steps_todo(&self) -> String {
+        let mut rtn = format!(
+            "- Fix the cargo build errors carried over from {}",
+            self.version_name
+        );
+        for (file, errors) in self.errors_by_file() {
+            rtn.push_str(&format!("\n  - {file}"));
+            for error in errors {
+                rtn.push_str(&format!(
+                    "\n    - [ ] line {} [{}] {}",
+                    error.line.map(|x| x.to_string()).unwrap_or("?".to_string()),
+                    error.code.as_deref().unwrap_or("-"),
+                    error.message.lines().next().unwrap_or_default()
+                ));
+            }
+        }
+        rtn.push('\n');
+        rtn
+    }
+}
+
+pub fn get_diagnostics_report_path(workspace_dir: impl AsRef<Path>, version_name: &str) -> PathBuf {
+    workspace_dir
+        .as_ref()
+        .join("diagnostics")
+        .join(format!("{version_name}.json"))
+}
+
+pub async fn write_diagnostics_report(
+    workspace_dir: impl AsRef<Path>,
+    report: &DiagnosticsReport,
+) -> eyre::Result<PathBuf> {
+    let path = get_diagnostics_report_path(workspace_dir, &report.version_name);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, serde_json::to_string_pretty(report)?).await?;
+    Ok(path)
+}
+
+pub async fn read_diagnostics_report(path: impl AsRef<Path>) -> eyre::Result<DiagnosticsReport> {
+    let path = path.as_ref();
+    let report = tokio::fs::read_to_string(path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    serde_json::from_str(&report).context(format!("Parsing {}", path.display()))
+}
+
+/// Build a version and collect its diagnostics with paths relative to the version directory.
+///
+/// Versions that aren't workspace members can't be built in place, so they are copied to a
+/// scratch directory outside the workspace first.
+pub async fn build_version_for_diagnostics(
+    workspace_dir: impl AsRef<Path>,
+    version_name: &str,
+) -> eyre::Result<DiagnosticsReport> {
+    let workspace_dir = workspace_dir.as_ref();
+    let version_dir = workspace_dir.join(version_name);
+    let is_member = read_workspace_cargo_toml(workspace_dir)
+        .await?
+        .workspace
+        .is_some_and(|x| x.members.iter().any(|member| member == version_name));
+    let (build_dir, prefix) = if is_member {
+        (version_dir.clone(), format!("{version_name}/"))
+    } else {
+        let scratch_dir = std::env::temp_dir()
+            .join("nanuak-diagnostics")
+            .join(version_name);
+        if tokio::fs::try_exists(&scratch_dir).await? {
+            tokio::fs::remove_dir_all(&scratch_dir).await?;
+        }
+        info!(
+            "{} is not a workspace member, building a copy in {}",
+            version_name,
+            scratch_dir.display()
+        );
+        copy_version_dir(&version_dir, &scratch_dir).await?;
+        pin_path_dependencies(&scratch_dir, &version_dir).await?;
+        (scratch_dir, String::new())
+    };
+    let manifest_path = build_dir.join("Cargo.toml").to_string_lossy().to_string();
+    let run = run_cargo_with_diagnostics(&build_dir, &["build", "--manifest-path", &manifest_path])
+        .await?;
+    Ok(DiagnosticsReport {
+        version_name: version_name.to_string(),
+        success: run.success,
+        diagnostics: run
+            .diagnostics
+            .into_iter()
+            .map(|mut x| {
+                x.file = x
+                    .file
+                    .map(|file| file.strip_prefix(&prefix).unwrap_or(&file).to_string());
+                x
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed down `cargo build --message-format=json` output for a version with two type
+    /// errors in main.rs, an unresolved import in lib.rs and an unused variable warning.
+    const CARGO_JSON: &str = r#"{"reason":"compiler-artifact","package_id":"path+file:///ws/v002#0.1.0","target":{"name":"v002"}}
+{"reason":"compiler-message","message":{"level":"error","code":{"code":"E0308","explanation":null},"message":"mismatched types","spans":[{"file_name":"v002/src/lib.rs","line_start":1,"is_primary":false},{"file_name":"v002/src/main.rs","line_start":3,"is_primary":true}],"rendered":"error[E0308]: mismatched types"}}
+{"reason":"compiler-message","message":{"level":"warning","code":{"code":"unused_variables","explanation":null},"message":"unused variable: `x`","spans":[{"file_name":"v002/src/main.rs","line_start":2,"is_primary":true}],"rendered":"warning: unused variable"}}
+{"reason":"compiler-message","message":{"level":"error","code":{"code":"E0432","explanation":null},"message":"unresolved import `v001::gone`\nno `gone` in the root","spans":[{"file_name":"v002/src/lib.rs","line_start":1,"is_primary":true}],"rendered":"error[E0432]: unresolved import"}}
+{"reason":"compiler-message","message":{"level":"error","code":{"code":"E0308","explanation":null},"message":"mismatched types","spans":[{"file_name":"v002/src/main.rs","line_start":7,"is_primary":true}],"rendered":"error[E0308]: mismatched types"}}
+{"reason":"compiler-message","message":{"level":"error","code":null,"message":"aborting due to 3 previous errors","spans":[],"rendered":"error: aborting due to 3 previous errors"}}
+not json
+{"reason":"build-finished","success":false}
+"#;
+
+    fn get_report() -> DiagnosticsReport {
+        DiagnosticsReport {
+            version_name: "v002".to_string(),
+            success: false,
+            diagnostics: parse_cargo_messages(CARGO_JSON)
+                .into_iter()
+                .map(|mut x| {
+                    x.file = x
+                        .file
+                        .map(|file| file.trim_start_matches("v002/").to_string());
+                    x
+                })
+                .collect(),
+        }
+    }
+
+    fn get_locations(errors: &[&CargoDiagnostic]) -> Vec<String> {
+        errors.iter().map(|x| x.location()).collect()
+    }
+
+    #[test]
+    fn parses_compiler_messages_at_their_primary_span() {
+        let diagnostics = parse_cargo_messages(CARGO_JSON);
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics[0].location(), "v002/src/main.rs:3");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E0308"));
+        assert_eq!(
+            diagnostics.iter().filter(|x| x.is_error()).count(),
+            3,
+            "the aborting summary is not an error of its own"
+        );
+    }
+
+    #[test]
+    fn groups_errors_by_code_and_by_file() {
+        let report = get_report();
+        let by_code = report.errors_by_code();
+        assert_eq!(by_code.keys().collect_vec(), vec!["E0308", "E0432"]);
+        assert_eq!(
+            get_locations(&by_code["E0308"]),
+            vec!["src/main.rs:3", "src/main.rs:7"]
+        );
+        let by_file = report.errors_by_file();
+        assert_eq!(
+            by_file.keys().collect_vec(),
+            vec!["src/lib.rs", "src/main.rs"]
+        );
+        assert_eq!(get_locations(&by_file["src/lib.rs"]), vec!["src/lib.rs:1"]);
+        assert_eq!(by_file["src/main.rs"].len(), 2);
+        assert_eq!(
+            report.summarize(),
+            "v002: 3 errors, 1 warnings\n  src/main.rs:3 [E0308] mismatched types\n  src/lib.rs:1 [E0432] unresolved import `v001::gone`\nno `gone` in the root\n  src/main.rs:7 [E0308] mismatched types\nBy code:\n  E0308: 2\n  E0432: 1\nBy file:\n  src/lib.rs: 1\n  src/main.rs: 2"
+        );
+    }
+
+    #[test]
+    fn renders_errors_as_next_steps_todos() {
+        assert_eq!(
+            get_report().to_next_steps_todo(),
+            "- Fix the cargo build errors carried over from v002\n  - src/lib.rs\n    - [ ] line 1 [E0432] unresolved import `v001::gone`\n  - src/main.rs\n    - [ ] line 3 [E0308] mismatched types\n    - [ ] line 7 [E0308] mismatched types\n"
+        );
+    }
+
+    #[test]
+    fn keeps_only_diagnostics_in_the_version_dir() {
+        let mut run = CargoRun {
+            success: false,
+            diagnostics: parse_cargo_messages(CARGO_JSON),
+        };
+        run.diagnostics[1].file = Some("nanuak-core/src/lib.rs".to_string());
+        run.diagnostics[2].file = None;
+        run.retain_diagnostics_in_dir("v002/");
+        assert_eq!(
+            run.diagnostics.iter().map(|x| x.location()).collect_vec(),
+            vec!["v002/src/main.rs:3", "<unknown>", "v002/src/main.rs:7"]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::ArgAction;
+use clap::Parser;
+use clap::Subcommand;
+
+use crate::batch_define::BatchOutputFormat;
+use crate::server::DEFAULT_SERVE_ADDR;
+use crate::state_graph::DiagramFormat;
+use crate::state_graph::StateMachine;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Nanuak dictionary")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Append every state transition to this file as JSON lines
+    #[arg(long, global = true)]
+    pub transition_log: Option<PathBuf>,
+    /// Record every answer and transition into this dir so the session can be replayed
+    #[arg(long, global = true, conflicts_with = "transition_log")]
+    pub record: Option<PathBuf>,
+    /// Log more, repeat for even more
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Log less, repeat for even less
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub quiet: u8,
+    /// Also log to a file in logs/ under the workspace
+    #[arg(long, global = true)]
+    pub log_file: bool,
+}
+
+impl Cli {
+    /// Steps to move the log level by, up for `-v` and down for `-q`.
+    pub fn get_verbosity(&self) -> i8 {
+        self.verbose.min(5) as i8 - self.quiet.min(5) as i8
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the dictionary over HTTP
+    Serve {
+        #[arg(long, default_value = DEFAULT_SERVE_ADDR)]
+        addr: SocketAddr,
+        /// Defaults to dictionary.path from the config or dictionary.json in the root dir
+        #[arg(long)]
+        dictionary: Option<PathBuf>,
+    },
+    /// Define every word in a newline separated list
+    Define {
+        /// Read from stdin when omitted or "-"
+        file: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = BatchOutputFormat::Table)]
+        format: BatchOutputFormat,
+        /// Defaults to dictionary.path from the config or dictionary.json in the root dir
+        #[arg(long)]
+        dictionary: Option<PathBuf>,
+    },
+    /// Replay a recorded session against a fresh copy of its workspace
+    Replay {
+        /// The dir given to --record
+        recording: PathBuf,
+        /// Copy this workspace instead of the one the session was recorded in
+        #[arg(long)]
+        workspace: Option<PathBuf>,
+    },
+    /// Print a state machine as a diagram, or check transition logs against it
+    States {
+        #[arg(long, value_enum, default_value_t = StateMachine::App)]
+        machine: StateMachine,
+        #[arg(long, value_enum, default_value_t = DiagramFormat::Mermaid)]
+        format: DiagramFormat,
+        /// Fail if these transition logs contain transitions the machine does not declare
+        #[arg(long)]
+        check: Vec<PathBuf>,
+    },
+    /// Inspect the layered config
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print every resolved value along with where it was set
+    Show,
+}
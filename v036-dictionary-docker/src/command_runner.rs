@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tracing::info;
+
+/// A program invocation, kept as data so it can be shown, recorded or run.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub current_dir: Option<PathBuf>,
+}
+
+impl CommandSpec {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            current_dir: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn current_dir(mut self, current_dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(current_dir.into());
+        self
+    }
+
+    pub fn display(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(|x| x.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[async_trait::async_trait]
+pub trait CommandRunner: Send + Sync {
+    async fn run(&self, command: &CommandSpec) -> eyre::Result<CommandOutput>;
+}
+
+/// Runs commands for real, inheriting the terminal so long builds show their progress.
+///
+/// Stderr is echoed as it arrives and also kept, so failures can say what went wrong.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessCommandRunner;
+
+#[async_trait::async_trait]
+impl CommandRunner for ProcessCommandRunner {
+    async fn run(&self, command: &CommandSpec) -> eyre::Result<CommandOutput> {
+        info!("Running {}", command.display());
+        let mut process = tokio::process::Command::new(&command.program);
+        process.args(&command.args).stderr(Stdio::piped());
+        if let Some(current_dir) = &command.current_dir {
+            process.current_dir(current_dir);
+        }
+        let mut child = process
+            .spawn()
+            .context(format!("Running {}", command.display()))?;
+        let mut stderr = String::new();
+        if let Some(child_stderr) = child.stderr.take() {
+            let mut lines = BufReader::new(child_stderr).lines();
+            while let Some(line) = lines.next_line().await? {
+                eprintln!("{line}");
+                stderr.push_str(&line);
+                stderr.push('\n');
+            }
+        }
+        let status = child
+            .wait()
+            .await
+            .context(format!("Running {}", command.display()))?;
+        Ok(CommandOutput {
+            success: status.success(),
+            stdout: String::new(),
+            stderr,
+        })
+    }
+}
+
+/// Records commands instead of running them and reports success for each.
+#[derive(Debug, Default)]
+pub struct RecordingCommandRunner {
+    pub commands: Mutex<Vec<CommandSpec>>,
+}
+
+impl RecordingCommandRunner {
+    pub fn recorded(&self) -> Vec<CommandSpec> {
+        self.commands.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandRunner for RecordingCommandRunner {
+    async fn run(&self, command: &CommandSpec) -> eyre::Result<CommandOutput> {
+        self.commands.lock().unwrap().push(command.clone());
+        Ok(CommandOutput {
+            success: true,
+            stdout: String::new(),
+            stderr: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn process_runner_keeps_stderr() -> eyre::Result<()> {
+        let command = CommandSpec::new("sh")
+            .arg("-c")
+            .arg("echo building; echo no such image >&2; exit 1");
+        let output = ProcessCommandRunner.run(&command).await?;
+        assert!(!output.success);
+        assert_eq!(output.stderr, "no such image\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recording_runner_records_without_running() -> eyre::Result<()> {
+        let runner = RecordingCommandRunner::default();
+        let command = CommandSpec::new("definitely-not-a-program").arg("--flag");
+        let output = runner.run(&command).await?;
+        assert!(output.success);
+        assert_eq!(runner.recorded(), vec![command]);
+        Ok(())
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use eyre::Context;
+use eyre::OptionExt;
+use eyre::bail;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::logging::LogFormat;
+use nanuak_core::logging::LogOptions;
+use nanuak_core::logging::adjust_level;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+
+use crate::normalize::MatchOptions;
+
+/// Every setting with its default, the first layer of the config.
+pub const DEFAULT_CONFIG: &str = include_str!("../config.default.toml");
+pub const USER_CONFIG_FILE_NAME: &str = "config.toml";
+pub const WORKSPACE_CONFIG_FILE_NAME: &str = "nanuak.toml";
+pub const LOG_DIR_NAME: &str = "logs";
+
+/// Environment variables and the config key each overrides, applied after every file.
+pub const ENV_OVERRIDES: [(&str, &str); 11] = [
+    ("NANUAK_DICTIONARY_PATH", "dictionary.path"),
+    ("NANUAK_DICTIONARY_SOURCES", "dictionary.sources"),
+    ("OLLAMA_HOST", "ollama.url"),
+    ("NANUAK_OLLAMA_MODEL", "ollama.model"),
+    ("NANUAK_OLLAMA_EMBEDDING_MODEL", "ollama.embedding_model"),
+    ("NANUAK_LOG_LEVEL", "log.level"),
+    ("NANUAK_LOG_FORMAT", "log.format"),
+    ("NANUAK_LOG_FILE", "log.file"),
+    ("NANUAK_TEMPLATE_VERSION", "template.default_version"),
+    ("NANUAK_NORMALIZATION_FORM", "matching.form"),
+    ("NANUAK_IGNORE_DIACRITICS", "matching.ignore_diacritics"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub dictionary: DictionaryConfig,
+    pub ollama: OllamaConfig,
+    pub log: LogConfig,
+    #[serde(default)]
+    pub template: TemplateConfig,
+    /// How words are compared when looking them up or searching.
+    pub matching: MatchOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DictionaryConfig {
+    pub path: Option<PathBuf>,
+    /// Only entries from these sources are loaded, or every entry when empty.
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OllamaConfig {
+    pub url: String,
+    pub model: String,
+    pub embedding_model: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,
+    pub format: LogFormat,
+    #[serde(default)]
+    pub file: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// The version offered first when picking a version to copy.
+    pub default_version: Option<String>,
+}
+
+impl Config {
+    /// The dir under the workspace to log to when either the config or `--log-file` asks for it.
+    pub async fn get_log_file_dir(&self, log_file: bool) -> eyre::Result<Option<PathBuf>> {
+        if !log_file && !self.log.file {
+            return Ok(None);
+        }
+        let workspace_dir = get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir()
+            .await
+            .context("Finding the workspace to put the log file in")?;
+        Ok(Some(workspace_dir.join(LOG_DIR_NAME)))
+    }
+
+    /// The configured log options adjusted by `-v` and `-q`, also logging to `file_dir` if given.
+    pub fn get_log_options(
+        &self,
+        verbosity: i8,
+        file_dir: Option<PathBuf>,
+    ) -> eyre::Result<LogOptions> {
+        let level = LevelFilter::from_str(&self.log.level)
+            .context(format!("Parsing log level {:?}", self.log.level))?;
+        Ok(LogOptions {
+            level: adjust_level(level, verbosity),
+            format: self.log.format,
+            file_dir,
+        })
+    }
+
+    pub fn is_default_template_version(&self, version_dir: &Path) -> bool {
+        self.template
+            .default_version
+            .as_deref()
+            .is_some_and(|name| version_dir.file_name() == Some(OsStr::new(name)))
+    }
+}
+
+/// Where a config value was set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    Defaults,
+    User(PathBuf),
+    Workspace(PathBuf),
+    Env(String),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Defaults => write!(f, "defaults"),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::Workspace(path) => write!(f, "workspace config {}", path.display()),
+            Self::Env(var) => write!(f, "env {var}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigLayer {
+    pub origin: ConfigOrigin,
+    pub table: toml::Table,
+}
+
+impl ConfigLayer {
+    pub fn parse(origin: ConfigOrigin, content: &str) -> eyre::Result<Self> {
+        let table = toml::from_str(content).context(format!("Parsing {origin}"))?;
+        Ok(Self { origin, table })
+    }
+
+    pub async fn read(origin: ConfigOrigin, path: &Path) -> eyre::Result<Option<Self>> {
+        if !tokio::fs::try_exists(path).await? {
+            return Ok(None);
+        }
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .context(format!("Reading {origin}"))?;
+        Ok(Some(Self::parse(origin, &content)?))
+    }
+}
+
+/// One layer per variable in [`ENV_OVERRIDES`] that is set, so each value keeps its own origin.
+pub fn get_env_layers() -> Vec<ConfigLayer> {
+    let mut layers = Vec::new();
+    for (var, key) in ENV_OVERRIDES {
+        let Ok(value) = std::env::var(var) else {
+            continue;
+        };
+        let value = parse_env_value(key, value);
+        let mut table = toml::Table::new();
+        insert_dotted(&mut table, key, value).expect("Override keys are plain section.name pairs");
+        layers.push(ConfigLayer {
+            origin: ConfigOrigin::Env(var.to_string()),
+            table,
+        });
+    }
+    layers
+}
+
+/// The value an environment variable sets `key` to, typed like the config file would have it.
+fn parse_env_value(key: &str, value: String) -> toml::Value {
+    match key {
+        "dictionary.sources" => toml::Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(|x| toml::Value::String(x.to_string()))
+                .collect(),
+        ),
+        "log.file" | "matching.ignore_diacritics" => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" => toml::Value::Boolean(true),
+            "0" | "false" | "no" => toml::Value::Boolean(false),
+            _ => toml::Value::String(value),
+        },
+        "matching.form" => toml::Value::String(value.to_lowercase()),
+        _ => toml::Value::String(value),
+    }
+}
+
+pub fn get_user_config_path() -> eyre::Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_eyre("No config dir for the current user")?
+        .join("nanuak")
+        .join(USER_CONFIG_FILE_NAME))
+}
+
+/// The config along with every value set by a layer and the layer it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    pub values: BTreeMap<String, (toml::Value, ConfigOrigin)>,
+}
+
+impl ResolvedConfig {
+    /// Merge the layers key by key, later layers winning.
+    pub fn resolve(layers: Vec<ConfigLayer>) -> eyre::Result<Self> {
+        let mut values = BTreeMap::new();
+        for layer in &layers {
+            for (key, value) in flatten(&layer.table) {
+                values.insert(key, (value, layer.origin.clone()));
+            }
+        }
+        let mut merged = toml::Table::new();
+        for (key, (value, origin)) in &values {
+            insert_dotted(&mut merged, key, value.clone())
+                .context(format!("Merging {key} from {origin}"))?;
+        }
+        let config = toml::Value::Table(merged).try_into().context(format!(
+            "Resolving config from {}",
+            layers
+                .iter()
+                .map(|x| x.origin.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))?;
+        Ok(Self { config, values })
+    }
+
+    /// Every resolved value as `key = value # origin`.
+    pub fn render(&self) -> String {
+        self.values
+            .iter()
+            .map(|(key, (value, origin))| format!("{key} = {value} # {origin}\n"))
+            .collect()
+    }
+}
+
+/// The leaves of `table` keyed by their dotted path. Arrays are leaves.
+fn flatten(table: &toml::Table) -> Vec<(String, toml::Value)> {
+    let mut rtn = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(inner) => rtn.extend(
+                flatten(inner)
+                    .into_iter()
+                    .map(|(inner_key, value)| (format!("{key}.{inner_key}"), value)),
+            ),
+            _ => rtn.push((key.clone(), value.clone())),
+        }
+    }
+    rtn
+}
+
+fn insert_dotted(table: &mut toml::Table, key: &str, value: toml::Value) -> eyre::Result<()> {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let inner = table
+                .entry(head)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let toml::Value::Table(inner) = inner else {
+                bail!("{head} is set to a value but {key} needs it to be a table");
+            };
+            insert_dotted(inner, rest, value)
+        }
+        None => {
+            table.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+}
+
+/// Read the defaults, the user config, the workspace config and the environment, in that order.
+pub async fn load_config() -> eyre::Result<ResolvedConfig> {
+    let mut layers = vec![ConfigLayer::parse(ConfigOrigin::Defaults, DEFAULT_CONFIG)?];
+    let user_config_path = get_user_config_path()?;
+    layers.extend(
+        ConfigLayer::read(
+            ConfigOrigin::User(user_config_path.clone()),
+            &user_config_path,
+        )
+        .await?,
+    );
+    if let Ok(workspace_dir) =
+        get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await
+    {
+        let workspace_config_path = workspace_dir.join(WORKSPACE_CONFIG_FILE_NAME);
+        layers.extend(
+            ConfigLayer::read(
+                ConfigOrigin::Workspace(workspace_config_path.clone()),
+                &workspace_config_path,
+            )
+            .await?,
+        );
+    }
+    layers.extend(get_env_layers());
+    ResolvedConfig::resolve(layers)
+}
+
+static CONFIG: OnceLock<ResolvedConfig> = OnceLock::new();
+
+/// Set the config every `get_config` call returns. Only the first call has an effect.
+pub fn set_config(config: ResolvedConfig) {
+    if CONFIG.set(config).is_err() {
+        warn!("Config was already set, ignoring the new one");
+    }
+}
+
+/// The config given to `set_config`, or just the defaults and environment when it wasn't called.
+pub fn get_resolved_config() -> &'static ResolvedConfig {
+    CONFIG.get_or_init(|| {
+        let get_defaults = || {
+            ConfigLayer::parse(ConfigOrigin::Defaults, DEFAULT_CONFIG)
+                .expect("The default config should parse")
+        };
+        let mut layers = vec![get_defaults()];
+        layers.extend(get_env_layers());
+        ResolvedConfig::resolve(layers).unwrap_or_else(|e| {
+            warn!("Ignoring the environment: {:#}", e);
+            ResolvedConfig::resolve(vec![get_defaults()])
+                .expect("The default config should resolve")
+        })
+    })
+}
+
+pub fn get_config() -> &'static Config {
+    &get_resolved_config().config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::NormalizationForm;
+
+    fn get_origin<'a>(resolved: &'a ResolvedConfig, key: &str) -> &'a ConfigOrigin {
+        &resolved.values[key].1
+    }
+
+    #[test]
+    fn later_layers_win_key_by_key() -> eyre::Result<()> {
+        let user_path = PathBuf::from("/home/someone/.config/nanuak/config.toml");
+        let workspace_path = PathBuf::from("/work/Nanuak-Dictionary/nanuak.toml");
+        let env = |var: &str, key: &str, value: &str| {
+            let mut table = toml::Table::new();
+            insert_dotted(&mut table, key, parse_env_value(key, value.to_string()))?;
+            eyre::Ok(ConfigLayer {
+                origin: ConfigOrigin::Env(var.to_string()),
+                table,
+            })
+        };
+        let resolved = ResolvedConfig::resolve(vec![
+            ConfigLayer::parse(ConfigOrigin::Defaults, DEFAULT_CONFIG)?,
+            ConfigLayer::parse(
+                ConfigOrigin::User(user_path.clone()),
+                "[ollama]\nmodel = \"user-model\"\nurl = \"http://user:11434\"\n\n[log]\nlevel = \"debug\"\n",
+            )?,
+            ConfigLayer::parse(
+                ConfigOrigin::Workspace(workspace_path.clone()),
+                "[ollama]\nmodel = \"workspace-model\"\n\n[dictionary]\nsources = [\"wiktionary\"]\n",
+            )?,
+            env("NANUAK_OLLAMA_MODEL", "ollama.model", "env-model")?,
+            env(
+                "NANUAK_IGNORE_DIACRITICS",
+                "matching.ignore_diacritics",
+                "yes",
+            )?,
+            env("NANUAK_NORMALIZATION_FORM", "matching.form", "NFC")?,
+        ])?;
+
+        assert_eq!(resolved.config.ollama.model, "env-model");
+        assert_eq!(
+            get_origin(&resolved, "ollama.model"),
+            &ConfigOrigin::Env("NANUAK_OLLAMA_MODEL".to_string())
+        );
+        assert_eq!(resolved.config.ollama.url, "http://user:11434");
+        assert_eq!(
+            get_origin(&resolved, "ollama.url"),
+            &ConfigOrigin::User(user_path)
+        );
+        assert_eq!(resolved.config.log.level, "debug");
+        assert_eq!(resolved.config.dictionary.sources, vec!["wiktionary"]);
+        assert_eq!(
+            get_origin(&resolved, "dictionary.sources"),
+            &ConfigOrigin::Workspace(workspace_path)
+        );
+        assert_eq!(resolved.config.ollama.embedding_model, "nomic-embed-text");
+        assert_eq!(
+            get_origin(&resolved, "ollama.embedding_model"),
+            &ConfigOrigin::Defaults
+        );
+        assert_eq!(
+            resolved.config.matching,
+            MatchOptions {
+                form: NormalizationForm::Nfc,
+                ignore_diacritics: true,
+            }
+        );
+        assert!(
+            resolved
+                .render()
+                .contains("matching.form = \"nfc\" # env NANUAK_NORMALIZATION_FORM\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn defaults_resolve_on_their_own() -> eyre::Result<()> {
+        let resolved = ResolvedConfig::resolve(vec![ConfigLayer::parse(
+            ConfigOrigin::Defaults,
+            DEFAULT_CONFIG,
+        )?])?;
+        assert_eq!(resolved.config.matching, MatchOptions::default());
+        assert_eq!(resolved.config.template, TemplateConfig::default());
+        assert!(
+            resolved
+                .values
+                .values()
+                .all(|(_, origin)| origin == &ConfigOrigin::Defaults)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn flattened_keys_insert_back_into_the_same_table() -> eyre::Result<()> {
+        let table: toml::Table =
+            toml::from_str("top = 1\n\n[a]\nlist = [1, 2]\n\n[a.b]\nc = \"d\"\n")?;
+        let flattened = flatten(&table);
+        assert_eq!(
+            flattened
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a.b.c", "a.list", "top"]
+        );
+        let mut rebuilt = toml::Table::new();
+        for (key, value) in flattened {
+            insert_dotted(&mut rebuilt, &key, value)?;
+        }
+        assert_eq!(rebuilt, table);
+        Ok(())
+    }
+
+    #[test]
+    fn dotted_key_under_a_value_is_rejected() -> eyre::Result<()> {
+        let mut table: toml::Table = toml::from_str("log = \"info\"\n")?;
+        assert!(insert_dotted(&mut table, "log.level", toml::Value::from("debug")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn env_values_are_typed_like_the_config_file() {
+        assert_eq!(
+            parse_env_value("dictionary.sources", "a, b,,".to_string()),
+            toml::Value::Array(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(
+            parse_env_value("log.file", "TRUE".to_string()),
+            toml::Value::Boolean(true)
+        );
+        assert_eq!(
+            parse_env_value("matching.ignore_diacritics", "0".to_string()),
+            toml::Value::Boolean(false)
+        );
+        assert_eq!(
+            parse_env_value("ollama.model", "Llama".to_string()),
+            toml::Value::String("Llama".to_string())
+        );
+    }
+}
@@ -2,91 +2,108 @@ use std::path::PathBuf;
 
 use cloud_terrastodon_core_user_input::prelude::Choice;
 use cloud_terrastodon_core_user_input::prelude::FzfArgs;
-use cloud_terrastodon_core_user_input::prelude::pick;
 use eyre::Context;
 use eyre::OptionExt;
 use eyre::bail;
 use itertools::Itertools;
+use nanuak_core::cargo_toml;
+use nanuak_core::extract_next_version_number;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer_opt;
+use nanuak_core::version_name::get_recommended_suffix;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
-use tracing::warn;
-use v006_create_new_version::extract_next_version_number;
-use v006_create_new_version::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
-use v006_create_new_version::get_versions;
-use v006_create_new_version::is_valid_version_name;
-use v006_create_new_version::prompt_next_version_name;
-use v007_create_new_version::cargo_toml;
 
+use crate::cargo_diagnostics::run_cargo_with_diagnostics;
+use crate::cargo_diagnostics::summarize_diagnostics;
+use crate::config::get_config;
+use crate::prompt::pick_answer;
 use crate::state::State;
+use crate::state_graph::StateGraph;
+use crate::workspace::copy_version_dir;
+use crate::workspace::get_missing_paths;
+use crate::workspace::prompt_valid_next_version_name;
+use crate::workspace::read_workspace_cargo_toml;
+use crate::workspace::remove_paths;
+use crate::workspace::rename_version_crate_in_main;
+use crate::workspace::rename_version_package;
+use crate::workspace::write_workspace_cargo_toml;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
 pub enum CreateNewVersionState {
     #[default]
+    #[state(describe = "Determine workspace Cargo.toml path")]
     DetermineWorkspaceCargoTomlPath,
-    IdentifyNextVersionNumber {
+    #[state(describe = "Identify next version number")]
+    IdentifyNextVersionNumber { workspace_dir: PathBuf },
+    #[state(describe = "Identify template version")]
+    IdentifyTemplateVersion {
         workspace_dir: PathBuf,
+        next_version_number: usize,
     },
+    #[state(describe = "Identify next version name")]
     IdentifyNextVersionName {
         workspace_dir: PathBuf,
         next_version_number: usize,
+        template_version_name: String,
+        template_version_dir: PathBuf,
     },
-    IdentifyTemplateVersion {
+    #[state(describe = "Create new version from template", side_effects)]
+    CreateNewVersionFromTemplate {
         workspace_dir: PathBuf,
         next_version_name: String,
         next_version_dir: PathBuf,
+        template_version_name: String,
+        template_version_dir: PathBuf,
     },
-    CreateNewVersionFromTemplate {
+    #[state(describe = "Update version Cargo.toml", side_effects, idempotent)]
+    UpdateVersionCargoToml {
         workspace_dir: PathBuf,
         next_version_name: String,
         next_version_dir: PathBuf,
         template_version_name: String,
         template_version_dir: PathBuf,
+        /// The paths the copy added to a next version dir that already existed, or `None`
+        /// when the copy created the whole dir.
+        created_paths: Option<Vec<PathBuf>>,
     },
-    UpdateWorkspaceCargoToml {
+    #[state(describe = "Update main", side_effects, idempotent)]
+    UpdateMain {
         workspace_dir: PathBuf,
         next_version_name: String,
         next_version_dir: PathBuf,
         template_version_name: String,
         template_version_dir: PathBuf,
+        created_paths: Option<Vec<PathBuf>>,
     },
-    UpdateVersionCargoToml {
+    #[state(describe = "Update workspace Cargo.toml", side_effects, idempotent)]
+    UpdateWorkspaceCargoToml {
         workspace_dir: PathBuf,
         next_version_name: String,
         next_version_dir: PathBuf,
-        template_version_name: String,
-        template_version_dir: PathBuf,
+        created_paths: Option<Vec<PathBuf>>,
     },
-    UpdateMain {
+    #[state(describe = "Verify new version", skip = Self::Done)]
+    VerifyNewVersion {
         workspace_dir: PathBuf,
         next_version_name: String,
         next_version_dir: PathBuf,
-        template_version_name: String,
-        template_version_dir: PathBuf,
+        created_paths: Option<Vec<PathBuf>>,
+    },
+    #[state(describe = "Roll back new version", side_effects)]
+    RollBackNewVersion {
+        workspace_dir: PathBuf,
+        next_version_name: String,
+        next_version_dir: PathBuf,
+        created_paths: Option<Vec<PathBuf>>,
     },
+    #[state(describe = "Done", terminal)]
     Done,
 }
-#[async_trait::async_trait]
-impl State for CreateNewVersionState {
-    fn describe(&self) -> String {
-        match self {
-            Self::DetermineWorkspaceCargoTomlPath => "Determine workspace Cargo.toml path",
-            Self::IdentifyNextVersionNumber { .. } => "Identify next version number",
-            Self::IdentifyNextVersionName { .. } => "Identify next version name",
-            Self::IdentifyTemplateVersion { .. } => "Identify template version",
-            Self::CreateNewVersionFromTemplate { .. } => "Create new version from template",
-            Self::UpdateWorkspaceCargoToml { .. } => "Update workspace Cargo.toml",
-            Self::UpdateVersionCargoToml { .. } => "Update version Cargo.toml",
-            Self::UpdateMain { .. } => "Update main",
-            Self::Done => "Done",
-        }
-        .to_string()
-    }
-
-    async fn next(self) -> eyre::Result<Self>
-    where
-        Self: Sized,
-    {
+impl CreateNewVersionState {
+    async fn transition(self) -> eyre::Result<Self> {
         match self {
             Self::DetermineWorkspaceCargoTomlPath => {
                 info!("Find the root dir containing the versions");
@@ -104,54 +121,14 @@ impl State for CreateNewVersionState {
                     .await
                     .context(format!("Extracting next version number from {versions:?}"))?;
 
-                Ok(Self::IdentifyNextVersionName {
-                    workspace_dir,
-                    next_version_number,
-                })
-            }
-            Self::IdentifyNextVersionName {
-                workspace_dir,
-                next_version_number,
-            } => {
-                info!(
-                    "Prompt the user for the name of the next version, hinting the next version number"
-                );
-                let mut user_supplied_next_version_name =
-                    prompt_next_version_name(next_version_number).await?;
-
-                info!("Repeat prompt until valid input received");
-                while let Err(e) = is_valid_version_name(&user_supplied_next_version_name) {
-                    warn!("Error: {}", e);
-                    user_supplied_next_version_name =
-                        prompt_next_version_name(next_version_number).await?;
-                }
-                let validated_next_version_name = user_supplied_next_version_name;
-
-                info!("Identify the next version directory path");
-                let next_version_dir = workspace_dir.join(&validated_next_version_name);
-
-                info!("If the directory already exists, confirm y/n to proceed");
-                if next_version_dir.exists() {
-                    let proceed = dialoguer::Confirm::new()
-                        .with_prompt(format!(
-                            "Directory {} already exists. Proceed?",
-                            next_version_dir.display()
-                        ))
-                        .interact()?;
-                    if !proceed {
-                        bail!("User chose not to proceed");
-                    }
-                }
                 Ok(Self::IdentifyTemplateVersion {
                     workspace_dir,
-                    next_version_name: validated_next_version_name,
-                    next_version_dir,
+                    next_version_number,
                 })
             }
             Self::IdentifyTemplateVersion {
                 workspace_dir,
-                next_version_name,
-                next_version_dir,
+                next_version_number,
             } => {
                 let versions = get_versions(&workspace_dir).await?;
                 let mut choices = versions
@@ -162,15 +139,16 @@ impl State for CreateNewVersionState {
                     })
                     .collect_vec();
                 choices.reverse();
-                let chosen = pick(FzfArgs {
+                let config = get_config();
+                choices.sort_by_key(|choice| !config.is_default_template_version(&choice.value));
+                let chosen = pick_answer(FzfArgs {
                     choices,
                     header: Some("Choose a version to copy".to_string()),
                     prompt: None,
                 })?;
-                Ok(Self::CreateNewVersionFromTemplate {
+                Ok(Self::IdentifyNextVersionName {
                     workspace_dir,
-                    next_version_name,
-                    next_version_dir,
+                    next_version_number,
                     template_version_name: chosen
                         .value
                         .file_name()
@@ -180,6 +158,36 @@ impl State for CreateNewVersionState {
                     template_version_dir: chosen.value,
                 })
             }
+            Self::IdentifyNextVersionName {
+                workspace_dir,
+                next_version_number,
+                template_version_name,
+                template_version_dir,
+            } => {
+                info!("Recommend the suffix of {}", template_version_name);
+                let recommended_suffix = get_recommended_suffix(&template_version_name);
+
+                info!(
+                    "Prompt the user for the name of the next version, hinting the next version number"
+                );
+                let validated_next_version_name = prompt_valid_next_version_name(
+                    &workspace_dir,
+                    next_version_number,
+                    recommended_suffix.as_deref(),
+                )
+                .await?;
+
+                info!("Identify the next version directory path");
+                let next_version_dir = workspace_dir.join(&validated_next_version_name);
+
+                Ok(Self::CreateNewVersionFromTemplate {
+                    workspace_dir,
+                    next_version_name: validated_next_version_name,
+                    next_version_dir,
+                    template_version_name,
+                    template_version_dir,
+                })
+            }
             Self::CreateNewVersionFromTemplate {
                 workspace_dir,
                 next_version_name,
@@ -187,7 +195,11 @@ impl State for CreateNewVersionState {
                 template_version_name,
                 template_version_dir,
             } => {
-                v006_create_new_version::copy_dir_all(&template_version_dir, &next_version_dir)
+                let created_paths = match tokio::fs::try_exists(&next_version_dir).await? {
+                    true => Some(get_missing_paths(&template_version_dir, &next_version_dir).await?),
+                    false => None,
+                };
+                copy_version_dir(&template_version_dir, &next_version_dir)
                     .await
                     .context("Copying the reference version to the new version")?;
                 info!(
@@ -195,23 +207,67 @@ impl State for CreateNewVersionState {
                     template_version_dir.display()
                 );
 
-                Ok(Self::UpdateWorkspaceCargoToml {
+                Ok(Self::UpdateVersionCargoToml {
                     workspace_dir,
                     next_version_name,
                     next_version_dir,
                     template_version_name,
                     template_version_dir,
+                    created_paths,
                 })
             }
-            Self::UpdateWorkspaceCargoToml {
+            Self::UpdateVersionCargoToml {
                 workspace_dir,
                 next_version_name,
                 next_version_dir,
                 template_version_name,
                 template_version_dir,
+                created_paths,
+            } => {
+                info!(
+                    "replace the old version name in {}/Cargo.toml with the new version name",
+                    next_version_name
+                );
+                rename_version_package(&next_version_dir, &next_version_name).await?;
+
+                Ok(Self::UpdateMain {
+                    workspace_dir,
+                    next_version_name,
+                    next_version_dir,
+                    template_version_name,
+                    template_version_dir,
+                    created_paths,
+                })
+            }
+            Self::UpdateMain {
+                workspace_dir,
+                next_version_name,
+                next_version_dir,
+                template_version_name,
+                template_version_dir: _,
+                created_paths,
+            } => {
+                rename_version_crate_in_main(
+                    &next_version_dir,
+                    &template_version_name,
+                    &next_version_name,
+                )
+                .await?;
+                Ok(Self::UpdateWorkspaceCargoToml {
+                    workspace_dir,
+                    next_version_name,
+                    next_version_dir,
+                    created_paths,
+                })
+            }
+            Self::UpdateWorkspaceCargoToml {
+                workspace_dir,
+                next_version_name,
+                next_version_dir,
+                created_paths,
             } => {
                 let workspace_cargo_toml_path = workspace_dir.join("Cargo.toml");
-                if tokio::fs::try_exists(&workspace_cargo_toml_path).await? == false {
+                if !tokio::fs::try_exists(&workspace_cargo_toml_path).await? {
                     bail!(
                         "Cargo.toml not found at {}",
                         workspace_cargo_toml_path.display()
@@ -260,75 +316,174 @@ impl State for CreateNewVersionState {
                     toml::to_string(&workspace_cargo_toml)?,
                 )
                 .await?;
-                Ok(Self::UpdateVersionCargoToml {
+                Ok(Self::VerifyNewVersion {
                     workspace_dir,
                     next_version_name,
                     next_version_dir,
-                    template_version_name,
-                    template_version_dir,
+                    created_paths,
                 })
             }
-            Self::UpdateVersionCargoToml {
+            Self::VerifyNewVersion {
                 workspace_dir,
                 next_version_name,
                 next_version_dir,
-                template_version_name,
-                template_version_dir,
+                created_paths,
             } => {
+                info!("Check that {} compiles", next_version_name);
+                let mut run =
+                    run_cargo_with_diagnostics(&workspace_dir, &["check", "-p", &next_version_name])
+                        .await?;
+                if run.success {
+                    info!("{} checks cleanly", next_version_name);
+                    return Ok(Self::Done);
+                }
+                run.retain_diagnostics_in_dir(&next_version_name);
                 info!(
-                    "replace the old version name in {}/Cargo.toml with the new version name",
-                    next_version_name
+                    "cargo check failed for {}: {}",
+                    next_version_name,
+                    summarize_diagnostics(&run.diagnostics)
                 );
-                let cargo_toml_path = next_version_dir.join("Cargo.toml");
-                let cargo_toml =
-                    tokio::fs::read_to_string(&cargo_toml_path)
-                        .await
-                        .context(format!(
-                            "Reading {} as CargoToml",
-                            cargo_toml_path.display()
-                        ))?;
-                let mut cargo_toml: cargo_toml::CargoToml = toml::from_str(&cargo_toml).context(
-                    format!("Parsing {} as CargoToml", cargo_toml_path.display()),
+                let choice = answer_opt(
+                    &format!("What should happen to {next_version_name}?"),
+                    |prompt| {
+                        Ok(dialoguer::Select::new()
+                            .with_prompt(prompt)
+                            .items(&["Keep it and fix it by hand", "Roll it back"])
+                            .default(0)
+                            .interact_opt()?)
+                    },
                 )?;
-                cargo_toml
-                    .package
-                    .as_mut()
-                    .ok_or_eyre(format!(
-                        "Expected \"package\" to be present in {}",
-                        cargo_toml_path.display()
-                    ))?
-                    .name = next_version_name.to_string();
-                tokio::fs::write(&cargo_toml_path, toml::to_string(&cargo_toml)?).await?;
-
-                Ok(Self::UpdateMain {
-                    workspace_dir,
-                    next_version_name,
-                    next_version_dir,
-                    template_version_name,
-                    template_version_dir,
-                })
+                if choice == 0 {
+                    Ok(Self::Done)
+                } else {
+                    Ok(Self::RollBackNewVersion {
+                        workspace_dir,
+                        next_version_name,
+                        next_version_dir,
+                        created_paths,
+                    })
+                }
             }
-            Self::UpdateMain {
-                workspace_dir: _,
+            Self::RollBackNewVersion {
+                workspace_dir,
                 next_version_name,
                 next_version_dir,
-                template_version_name,
-                template_version_dir: _,
+                created_paths,
             } => {
-                let main_rs_path = next_version_dir.join("src").join("main.rs");
-                let find = template_version_name.replace("-","_");
-                let replace = next_version_name.replace("-","_");
-                info!("Update the main.rs file at {} to replace {} with {}", main_rs_path.display(), find, replace);
-                let main_rs = tokio::fs::read_to_string(&main_rs_path).await?;
-                let main_rs = main_rs.replace(&find, &replace);
-                tokio::fs::write(&main_rs_path, main_rs).await?;
+                info!("Remove {} from the workspace Cargo.toml", next_version_name);
+                let mut workspace_cargo_toml = read_workspace_cargo_toml(&workspace_dir).await?;
+                let workspace = workspace_cargo_toml
+                    .workspace
+                    .as_mut()
+                    .ok_or_eyre("No workspace")?;
+                workspace.members.retain(|member| member != &next_version_name);
+                workspace.dependencies.0.remove(&next_version_name);
+                write_workspace_cargo_toml(&workspace_dir, &workspace_cargo_toml).await?;
+
+                match created_paths {
+                    None => {
+                        info!("Remove {}", next_version_dir.display());
+                        tokio::fs::remove_dir_all(&next_version_dir).await?;
+                    }
+                    Some(created_paths) => {
+                        info!(
+                            "{} already existed, removing only the {} paths copied into it",
+                            next_version_dir.display(),
+                            created_paths.len()
+                        );
+                        remove_paths(&next_version_dir, &created_paths).await?;
+                    }
+                }
                 Ok(Self::Done)
             }
             Self::Done => Ok(Self::Done),
         }
     }
+}
+
+impl StateGraph for CreateNewVersionState {
+    fn get_examples() -> Vec<Self> {
+        let workspace_dir = PathBuf::new();
+        let next_version_name = String::new();
+        let next_version_dir = PathBuf::new();
+        let template_version_name = String::new();
+        let template_version_dir = PathBuf::new();
+        vec![
+            Self::DetermineWorkspaceCargoTomlPath,
+            Self::IdentifyNextVersionNumber {
+                workspace_dir: workspace_dir.clone(),
+            },
+            Self::IdentifyTemplateVersion {
+                workspace_dir: workspace_dir.clone(),
+                next_version_number: 0,
+            },
+            Self::IdentifyNextVersionName {
+                workspace_dir: workspace_dir.clone(),
+                next_version_number: 0,
+                template_version_name: template_version_name.clone(),
+                template_version_dir: template_version_dir.clone(),
+            },
+            Self::CreateNewVersionFromTemplate {
+                workspace_dir: workspace_dir.clone(),
+                next_version_name: next_version_name.clone(),
+                next_version_dir: next_version_dir.clone(),
+                template_version_name: template_version_name.clone(),
+                template_version_dir: template_version_dir.clone(),
+            },
+            Self::UpdateVersionCargoToml {
+                workspace_dir: workspace_dir.clone(),
+                next_version_name: next_version_name.clone(),
+                next_version_dir: next_version_dir.clone(),
+                template_version_name: template_version_name.clone(),
+                template_version_dir: template_version_dir.clone(),
+                created_paths: None,
+            },
+            Self::UpdateMain {
+                workspace_dir: workspace_dir.clone(),
+                next_version_name: next_version_name.clone(),
+                next_version_dir: next_version_dir.clone(),
+                template_version_name,
+                template_version_dir,
+                created_paths: None,
+            },
+            Self::UpdateWorkspaceCargoToml {
+                workspace_dir: workspace_dir.clone(),
+                next_version_name: next_version_name.clone(),
+                next_version_dir: next_version_dir.clone(),
+                created_paths: None,
+            },
+            Self::VerifyNewVersion {
+                workspace_dir: workspace_dir.clone(),
+                next_version_name: next_version_name.clone(),
+                next_version_dir: next_version_dir.clone(),
+                created_paths: None,
+            },
+            Self::RollBackNewVersion {
+                workspace_dir,
+                next_version_name,
+                next_version_dir,
+                created_paths: None,
+            },
+            Self::Done,
+        ]
+    }
 
-    fn is_terminal(&self) -> bool {
-        matches!(self, Self::Done)
+    fn get_transitions() -> Vec<(&'static str, &'static str)> {
+        vec![
+            (
+                "DetermineWorkspaceCargoTomlPath",
+                "IdentifyNextVersionNumber",
+            ),
+            ("IdentifyNextVersionNumber", "IdentifyTemplateVersion"),
+            ("IdentifyTemplateVersion", "IdentifyNextVersionName"),
+            ("IdentifyNextVersionName", "CreateNewVersionFromTemplate"),
+            ("CreateNewVersionFromTemplate", "UpdateVersionCargoToml"),
+            ("UpdateVersionCargoToml", "UpdateMain"),
+            ("UpdateMain", "UpdateWorkspaceCargoToml"),
+            ("UpdateWorkspaceCargoToml", "VerifyNewVersion"),
+            ("VerifyNewVersion", "Done"),
+            ("VerifyNewVersion", "RollBackNewVersion"),
+            ("RollBackNewVersion", "Done"),
+        ]
     }
 }
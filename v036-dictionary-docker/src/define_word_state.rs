@@ -4,28 +4,20 @@ use serde::Serialize;
 use crate::define_word;
 use crate::prompt_user_for_word;
 use crate::state::State;
+use crate::state_graph::StateGraph;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
 pub enum DefineWordState {
     #[default]
+    #[state(describe = "Prompt me for a word to define")]
     PromptingForWordToDefine,
+    #[state(describe = "Define a word", side_effects, idempotent)]
     DefiningWord { word: String },
+    #[state(describe = "Done", terminal)]
     Done,
 }
-#[async_trait::async_trait]
-impl State for DefineWordState {
-    fn describe(&self) -> String {
-        match self {
-            Self::PromptingForWordToDefine => "Prompt me for a word to define",
-            Self::DefiningWord { .. } => "Define a word",
-            Self::Done => "Done",
-        }.to_string()
-    }
-
-    async fn next(self) -> eyre::Result<Self>
-    where
-        Self: Sized,
-    {
+impl DefineWordState {
+    async fn transition(self) -> eyre::Result<Self> {
         match self {
             Self::PromptingForWordToDefine => {
                 let word = prompt_user_for_word().await?;
@@ -38,9 +30,23 @@ impl State for DefineWordState {
             Self::Done => Ok(Self::Done),
         }
     }
-    
-    fn is_terminal(&self) -> bool {
-        matches!(self, Self::Done)
+}
+
+impl StateGraph for DefineWordState {
+    fn get_examples() -> Vec<Self> {
+        vec![
+            Self::PromptingForWordToDefine,
+            Self::DefiningWord {
+                word: String::new(),
+            },
+            Self::Done,
+        ]
+    }
+
+    fn get_transitions() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("PromptingForWordToDefine", "DefiningWord"),
+            ("DefiningWord", "Done"),
+        ]
     }
-    
 }
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use itertools::Itertools;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::config::get_config;
+use crate::normalize::MatchOptions;
+use crate::normalize::normalize_for_matching;
+
+pub const DICTIONARY_FILE_NAME: &str = "dictionary.json";
+
+/// One definition of a word, matching the JSON schema in the v001 README.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DictionaryEntry {
+    pub word: String,
+    pub definition: String,
+    pub source: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Dictionary {
+    pub entries: Vec<DictionaryEntry>,
+    pub match_options: MatchOptions,
+    /// Normalised word to the indices of its entries.
+    pub word_index: HashMap<String, Vec<usize>>,
+    /// The normalised word and definition of each entry, so searching doesn't redo them per query.
+    pub search_keys: Vec<(String, String)>,
+}
+
+impl Dictionary {
+    pub fn new(entries: Vec<DictionaryEntry>) -> Self {
+        Self::with_match_options(entries, MatchOptions::default())
+    }
+
+    pub fn with_match_options(entries: Vec<DictionaryEntry>, match_options: MatchOptions) -> Self {
+        let mut word_index: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut search_keys = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let word = normalize_for_matching(&entry.word, &match_options);
+            let definition = normalize_for_matching(&entry.definition, &match_options);
+            word_index.entry(word.clone()).or_default().push(i);
+            search_keys.push((word, definition));
+        }
+        Self {
+            entries,
+            match_options,
+            word_index,
+            search_keys,
+        }
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .context(format!("Reading dictionary {}", path.display()))?;
+        let mut entries: Vec<DictionaryEntry> = serde_json::from_str(&contents)
+            .context(format!("Parsing dictionary {}", path.display()))?;
+        info!("Loaded {} entries from {}", entries.len(), path.display());
+        let sources = &get_config().dictionary.sources;
+        if !sources.is_empty() {
+            entries.retain(|entry| sources.contains(&entry.source));
+            info!("Kept {} entries from {}", entries.len(), sources.join(", "));
+        }
+        Ok(Self::with_match_options(entries, get_config().matching))
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        normalize_for_matching(text, &self.match_options)
+    }
+
+    /// Every entry for `word`, compared after normalisation.
+    pub fn define(&self, word: &str) -> Vec<&DictionaryEntry> {
+        self.word_index
+            .get(&self.normalize(word))
+            .map(|indices| indices.iter().map(|i| &self.entries[*i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Entries whose word or definition contains `query`, compared after normalisation.
+    pub fn search(&self, query: &str) -> Vec<&DictionaryEntry> {
+        let query = self.normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.entries
+            .iter()
+            .zip(&self.search_keys)
+            .filter(|(_, (word, definition))| word.contains(&query) || definition.contains(&query))
+            .map(|(entry, _)| entry)
+            .collect()
+    }
+
+    pub fn sources(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| entry.source.clone())
+            .unique()
+            .sorted()
+            .collect()
+    }
+}
+
+/// `dictionary.path` from the config if set, otherwise `dictionary.json` in the root dir.
+pub async fn get_dictionary_path() -> eyre::Result<PathBuf> {
+    if let Some(path) = &get_config().dictionary.path {
+        return Ok(path.clone());
+    }
+    Ok(
+        get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir()
+            .await?
+            .join(DICTIONARY_FILE_NAME),
+    )
+}
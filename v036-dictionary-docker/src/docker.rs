@@ -0,0 +1,173 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::bail;
+use tracing::info;
+
+use crate::command_runner::CommandRunner;
+use crate::command_runner::CommandSpec;
+
+pub const DOCKERFILE_NAME: &str = "Dockerfile";
+/// Docker reads `<Dockerfile>.dockerignore` next to the Dockerfile ahead of the one in the build
+/// context, which lets each version keep its own while the context stays the workspace root.
+pub const DOCKERIGNORE_NAME: &str = "Dockerfile.dockerignore";
+pub const BUILDER_IMAGE: &str = "rust:1-bookworm";
+pub const RUNTIME_IMAGE: &str = "debian:bookworm-slim";
+
+/// A two stage Dockerfile that builds only `version_name` from the workspace root.
+///
+/// `crate_dirs` is the version followed by everything it depends on by path. The builder stage
+/// replaces the workspace manifest with one listing just those crates so the other versions,
+/// which the `.dockerignore` leaves out, aren't needed.
+pub fn generate_dockerfile(version_name: &str, crate_dirs: &[String]) -> String {
+    let members = crate_dirs
+        .iter()
+        .map(|x| format!("\"{x}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut rtn = format!(
+        "# Generated by nanuak for {version_name}\n\
+         FROM {BUILDER_IMAGE} AS builder\n\
+         WORKDIR /workspace\n\
+         COPY Cargo.lock ./\n"
+    );
+    for crate_dir in crate_dirs {
+        rtn.push_str(&format!("COPY {crate_dir} ./{crate_dir}\n"));
+    }
+    rtn.push_str(&format!(
+        "RUN printf '[workspace]\\nresolver = \"2\"\\nmembers = [{members}]\\n' > Cargo.toml\n\
+         RUN cargo build --release -p {version_name}\n\
+         \n\
+         FROM {RUNTIME_IMAGE}\n\
+         RUN apt-get update \\\n    && apt-get install -y --no-install-recommends ca-certificates fzf \\\n    && rm -rf /var/lib/apt/lists/*\n\
+         COPY --from=builder /workspace/target/release/{version_name} /usr/local/bin/{version_name}\n\
+         ENTRYPOINT [\"/usr/local/bin/{version_name}\"]\n"
+    ));
+    rtn
+}
+
+/// Exclude everything from the build context except the crates the Dockerfile copies.
+pub fn generate_dockerignore(crate_dirs: &[String]) -> String {
+    let mut rtn = "*\n!Cargo.lock\n".to_string();
+    for crate_dir in crate_dirs {
+        rtn.push_str(&format!("!{crate_dir}\n{crate_dir}/target\n"));
+    }
+    rtn
+}
+
+pub fn get_dockerfile_path(workspace_dir: impl AsRef<Path>, version_name: &str) -> PathBuf {
+    workspace_dir
+        .as_ref()
+        .join(version_name)
+        .join(DOCKERFILE_NAME)
+}
+
+pub fn get_dockerignore_path(workspace_dir: impl AsRef<Path>, version_name: &str) -> PathBuf {
+    workspace_dir
+        .as_ref()
+        .join(version_name)
+        .join(DOCKERIGNORE_NAME)
+}
+
+pub fn get_image_tag(version_name: &str) -> String {
+    format!("nanuak/{}", version_name.to_lowercase())
+}
+
+pub fn docker_build_command(workspace_dir: impl AsRef<Path>, version_name: &str) -> CommandSpec {
+    let workspace_dir: PathBuf = workspace_dir.as_ref().to_path_buf();
+    CommandSpec::new("docker")
+        .arg("build")
+        .arg("--file")
+        .arg(
+            get_dockerfile_path(&workspace_dir, version_name)
+                .to_string_lossy()
+                .to_string(),
+        )
+        .arg("--tag")
+        .arg(get_image_tag(version_name))
+        .arg(workspace_dir.to_string_lossy().to_string())
+        .current_dir(workspace_dir)
+}
+
+/// The Dockerfile copies Cargo.lock, which isn't checked in, so make one when it is missing.
+pub async fn ensure_lockfile(
+    runner: &dyn CommandRunner,
+    workspace_dir: impl AsRef<Path>,
+) -> eyre::Result<()> {
+    let workspace_dir = workspace_dir.as_ref();
+    if tokio::fs::try_exists(workspace_dir.join("Cargo.lock")).await? {
+        return Ok(());
+    }
+    info!("Generate Cargo.lock in {}", workspace_dir.display());
+    let command = CommandSpec::new("cargo")
+        .arg("generate-lockfile")
+        .current_dir(workspace_dir);
+    let output = runner.run(&command).await?;
+    if !output.success {
+        bail!("{} failed\n{}", command.display(), output.stderr);
+    }
+    Ok(())
+}
+
+pub async fn docker_build(
+    runner: &dyn CommandRunner,
+    workspace_dir: impl AsRef<Path>,
+    version_name: &str,
+) -> eyre::Result<String> {
+    ensure_lockfile(runner, &workspace_dir).await?;
+    let command = docker_build_command(workspace_dir, version_name);
+    let output = runner.run(&command).await?;
+    if !output.success {
+        bail!("{} failed\n{}", command.display(), output.stderr);
+    }
+    Ok(get_image_tag(version_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::RecordingCommandRunner;
+
+    #[tokio::test]
+    async fn docker_build_generates_a_missing_lockfile_first() -> eyre::Result<()> {
+        let workspace_dir = tempfile::tempdir()?;
+        let workspace_dir = workspace_dir.path();
+        let runner = RecordingCommandRunner::default();
+        let tag = docker_build(&runner, workspace_dir, "v002-Next").await?;
+        assert_eq!(tag, "nanuak/v002-next");
+        assert_eq!(
+            runner.recorded(),
+            vec![
+                CommandSpec::new("cargo")
+                    .arg("generate-lockfile")
+                    .current_dir(workspace_dir),
+                docker_build_command(workspace_dir, "v002-Next"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn docker_build_uses_the_dockerfile_in_the_version_dir() -> eyre::Result<()> {
+        let workspace_dir = tempfile::tempdir()?;
+        let workspace_dir = workspace_dir.path();
+        tokio::fs::write(workspace_dir.join("Cargo.lock"), "").await?;
+        let runner = RecordingCommandRunner::default();
+        docker_build(&runner, workspace_dir, "v002-next").await?;
+        let recorded = runner.recorded();
+        assert_eq!(recorded.len(), 1);
+        let dockerfile_path = workspace_dir.join("v002-next").join(DOCKERFILE_NAME);
+        assert_eq!(
+            recorded[0].args,
+            vec![
+                "build".to_string(),
+                "--file".to_string(),
+                dockerfile_path.to_string_lossy().to_string(),
+                "--tag".to_string(),
+                "nanuak/v002-next".to_string(),
+                workspace_dir.to_string_lossy().to_string(),
+            ]
+        );
+        Ok(())
+    }
+}
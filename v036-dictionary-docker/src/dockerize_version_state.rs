@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::OptionExt;
+use eyre::bail;
+use itertools::Itertools;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::command_runner::ProcessCommandRunner;
+use crate::docker::DOCKERFILE_NAME;
+use crate::docker::DOCKERIGNORE_NAME;
+use crate::docker::docker_build;
+use crate::docker::docker_build_command;
+use crate::docker::generate_dockerfile;
+use crate::docker::generate_dockerignore;
+use crate::docker::get_dockerfile_path;
+use crate::docker::get_dockerignore_path;
+use crate::prompt::pick_answer;
+use crate::state::State;
+use crate::workspace::get_transitive_path_dependencies;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum DockerizeVersionState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose version to containerize")]
+    ChooseVersion { workspace_dir: PathBuf },
+    #[state(
+        describe = "Write Dockerfile and .dockerignore",
+        side_effects,
+        idempotent
+    )]
+    WriteDockerFiles {
+        workspace_dir: PathBuf,
+        version_name: String,
+    },
+    #[state(describe = "Confirm docker build")]
+    ConfirmDockerBuild {
+        workspace_dir: PathBuf,
+        version_name: String,
+    },
+    #[state(describe = "Run docker build", side_effects, idempotent, skip = Self::Done)]
+    RunDockerBuild {
+        workspace_dir: PathBuf,
+        version_name: String,
+    },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl DockerizeVersionState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseVersion { workspace_dir })
+            }
+            Self::ChooseVersion { workspace_dir } => {
+                let mut choices = get_versions(&workspace_dir)
+                    .await?
+                    .into_iter()
+                    .filter(|version| version.join("src").join("main.rs").exists())
+                    .map(|version| Choice {
+                        key: version.display().to_string(),
+                        value: version,
+                    })
+                    .collect_vec();
+                choices.reverse();
+                let chosen = pick_answer(FzfArgs {
+                    choices,
+                    header: Some("Choose a version to containerize".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::WriteDockerFiles {
+                    workspace_dir,
+                    version_name: chosen
+                        .value
+                        .file_name()
+                        .ok_or_eyre(format!("No file name for {:?}", chosen.value))?
+                        .to_string_lossy()
+                        .to_string(),
+                })
+            }
+            Self::WriteDockerFiles {
+                workspace_dir,
+                version_name,
+            } => {
+                let crate_dirs =
+                    get_transitive_path_dependencies(&workspace_dir, &version_name).await?;
+                info!("{} builds from {:?}", version_name, crate_dirs);
+
+                let dockerfile_path = get_dockerfile_path(&workspace_dir, &version_name);
+                let dockerignore_path = get_dockerignore_path(&workspace_dir, &version_name);
+                if tokio::fs::try_exists(&dockerfile_path).await?
+                    || tokio::fs::try_exists(&dockerignore_path).await?
+                {
+                    let overwrite = answer_opt(
+                        &format!(
+                            "Overwrite the existing {} and {} in {}?",
+                            DOCKERFILE_NAME,
+                            DOCKERIGNORE_NAME,
+                            workspace_dir.join(&version_name).display()
+                        ),
+                        |prompt| {
+                            Ok(dialoguer::Confirm::new()
+                                .with_prompt(prompt)
+                                .interact_opt()?)
+                        },
+                    )?;
+                    if !overwrite {
+                        bail!("Refusing to overwrite the existing docker files");
+                    }
+                }
+                tokio::fs::write(
+                    &dockerfile_path,
+                    generate_dockerfile(&version_name, &crate_dirs),
+                )
+                .await?;
+                tokio::fs::write(&dockerignore_path, generate_dockerignore(&crate_dirs)).await?;
+                info!(
+                    "Wrote {} and {}",
+                    dockerfile_path.display(),
+                    dockerignore_path.display()
+                );
+                Ok(Self::ConfirmDockerBuild {
+                    workspace_dir,
+                    version_name,
+                })
+            }
+            Self::ConfirmDockerBuild {
+                workspace_dir,
+                version_name,
+            } => {
+                let build = answer_opt(
+                    &format!(
+                        "Run {}?",
+                        docker_build_command(&workspace_dir, &version_name).display()
+                    ),
+                    |prompt| {
+                        Ok(dialoguer::Confirm::new()
+                            .with_prompt(prompt)
+                            .interact_opt()?)
+                    },
+                )?;
+                if build {
+                    Ok(Self::RunDockerBuild {
+                        workspace_dir,
+                        version_name,
+                    })
+                } else {
+                    Ok(Self::Done)
+                }
+            }
+            Self::RunDockerBuild {
+                workspace_dir,
+                version_name,
+            } => {
+                let tag =
+                    docker_build(&ProcessCommandRunner, &workspace_dir, &version_name).await?;
+                info!("Built image {}", tag);
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
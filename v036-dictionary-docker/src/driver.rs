@@ -0,0 +1,310 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::Utc;
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::Context;
+use eyre::eyre;
+use nanuak_core::prompt::get_answer_count;
+use nanuak_core::prompt::is_go_back;
+use nanuak_core::prompt::set_back_allowed;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tracing::Instrument;
+use tracing::info;
+use tracing::info_span;
+use tracing::warn;
+
+use crate::prompt::pick_answer;
+use crate::recovery::Interrupted;
+use crate::recovery::RecoveryCheckpoint;
+use crate::recovery::is_interrupt;
+use crate::recovery::is_interrupted;
+use crate::recovery::step_cancelled;
+use crate::recovery::write_checkpoint;
+use crate::session::with_session;
+use crate::state::State;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DriverOptions {
+    /// Append a `TransitionRecord` line here for every transition.
+    pub transition_log: Option<PathBuf>,
+}
+
+tokio::task_local! {
+    /// Set by `drive_with_options` for everything it drives, including nested state machines.
+    static DRIVER_OPTIONS: DriverOptions;
+}
+
+/// The options of the `drive_with_options` call this runs under, or the defaults outside one.
+pub fn get_driver_options() -> DriverOptions {
+    DRIVER_OPTIONS.try_with(|x| x.clone()).unwrap_or_default()
+}
+
+/// [`drive`] with `options` in effect for every step, including those of nested state machines,
+/// recording the actions it runs in a fresh session history.
+pub async fn drive_with_options<S: State>(state: S, options: DriverOptions) -> eyre::Result<S> {
+    DRIVER_OPTIONS
+        .scope(options, with_session(drive(state)))
+        .await
+}
+
+/// One line of the transition log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TransitionRecord {
+    pub at: DateTime<Utc>,
+    pub state_type: String,
+    pub before: serde_json::Value,
+    pub after: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub elapsed_ms: u128,
+}
+
+/// Run `state` until it is terminal.
+///
+/// Earlier states are kept on a stack so a prompt answered with [`nanuak_core::prompt::GoBack`]
+/// returns to the last state that asked something, with its answers intact.
+/// A state with side effects clears the stack since its changes can't be undone.
+///
+/// After Ctrl-C the state to carry on from is written to the recovery file and
+/// [`Interrupted`] is returned.
+///
+/// When a state fails the user picks a [`Recovery`], or goes back.
+pub async fn drive<S: State>(state: S) -> eyre::Result<S> {
+    let mut state = state;
+    // Each earlier state along with whether it prompted the user.
+    let mut previous_states: Vec<(S, bool)> = Vec::new();
+    let mut last_side_effect: Option<String> = None;
+    while !state.is_terminal() {
+        if is_interrupted() {
+            let checkpoint = RecoveryCheckpoint::new(&state)?;
+            return Err(stop_at_checkpoint(checkpoint).await?);
+        }
+        set_back_allowed(previous_states.iter().any(|(_, asked)| *asked));
+        let answers_before = get_answer_count();
+        let result = match step(state.clone()).await {
+            Err(e) if !is_go_back(&e) && !is_interrupt(&e) => match choose_recovery(&state, &e) {
+                Ok(Recovery::Retry) => {
+                    info!("Retrying: {}", state.describe());
+                    continue;
+                }
+                Ok(Recovery::Skip(next)) => {
+                    info!("Skipping to: {}", next.describe());
+                    Ok(next)
+                }
+                Ok(Recovery::Abort) => {
+                    let checkpoint = RecoveryCheckpoint::new(&state)?;
+                    return Err(stop_at_checkpoint(checkpoint).await?);
+                }
+                Err(prompt_error) if is_go_back(&prompt_error) || is_interrupt(&prompt_error) => {
+                    Err(prompt_error)
+                }
+                Err(_) => Err(e),
+            },
+            result => result,
+        };
+        match result {
+            Ok(next) => {
+                if state.has_side_effects() {
+                    previous_states.clear();
+                    last_side_effect = Some(state.describe());
+                } else {
+                    previous_states.push((state, get_answer_count() != answers_before));
+                }
+                state = next;
+            }
+            Err(e) if is_go_back(&e) => {
+                match previous_states.iter().rposition(|(_, asked)| *asked) {
+                    Some(i) => {
+                        let (previous, _) = previous_states.swap_remove(i);
+                        previous_states.truncate(i);
+                        info!("Going back to: {}", previous.describe());
+                        state = previous;
+                    }
+                    None => match &last_side_effect {
+                        Some(side_effect) => warn!(
+                            "Can't go back past {:?} since its changes can't be undone",
+                            side_effect
+                        ),
+                        None => warn!("There is nothing to go back to"),
+                    },
+                }
+            }
+            Err(e) if is_interrupt(&e) => {
+                let checkpoint = RecoveryCheckpoint::new(&state)?;
+                return Err(stop_at_checkpoint(checkpoint).await?);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    set_back_allowed(false);
+    Ok(state)
+}
+
+/// What to do about a state that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovery<S> {
+    /// Run the state again, only offered when it is idempotent.
+    Retry,
+    /// Carry on from the state it skips to.
+    Skip(S),
+    /// Write a checkpoint of the failed state to resume from later and stop.
+    Abort,
+}
+
+/// Show the report for a failed state and ask what to do about it.
+///
+/// Going back is offered by `pick_answer` when there is somewhere to go back to.
+pub fn choose_recovery<S: State>(state: &S, error: &eyre::Report) -> eyre::Result<Recovery<S>> {
+    eprintln!("{error:?}");
+    let mut choices = Vec::new();
+    if state.is_idempotent() {
+        choices.push(Choice {
+            key: "Retry".to_string(),
+            value: Recovery::Retry,
+        });
+    } else {
+        info!(
+            "{} can't be retried since it may have made changes",
+            state.describe()
+        );
+    }
+    if let Some(next) = state.skip() {
+        choices.push(Choice {
+            key: format!("Skip to {}", next.describe()),
+            value: Recovery::Skip(next),
+        });
+    }
+    choices.push(Choice {
+        key: "Abort and save a checkpoint to resume from".to_string(),
+        value: Recovery::Abort,
+    });
+    let chosen = pick_answer(FzfArgs {
+        choices,
+        header: Some(format!("{} failed, what now?", state.describe())),
+        prompt: None,
+    })?;
+    Ok(chosen.value)
+}
+
+async fn stop_at_checkpoint(checkpoint: RecoveryCheckpoint) -> eyre::Result<eyre::Report> {
+    set_back_allowed(false);
+    let checkpoint_path = write_checkpoint(&checkpoint).await?;
+    Ok(Interrupted { checkpoint_path }.into())
+}
+
+/// Run a single transition inside a span named after the state, timing it and logging it.
+///
+/// A second Ctrl-C drops the transition at its next await point, unless the state has side effects.
+/// Those always run to completion so the checkpoint written after them never predates half-made changes.
+pub async fn step<S: State>(state: S) -> eyre::Result<S> {
+    let span = info_span!("state", state = %state.describe());
+    let transition_log = get_driver_options().transition_log;
+    let before = match transition_log {
+        Some(_) => Some(serde_json::to_value(&state)?),
+        None => None,
+    };
+    let started = Instant::now();
+    let result = if state.has_side_effects() {
+        state.next().instrument(span.clone()).await
+    } else {
+        tokio::select! {
+            result = state.next().instrument(span.clone()) => result,
+            _ = step_cancelled() => Err(eyre!("Cancelled by Ctrl-C")),
+        }
+    };
+    let elapsed = started.elapsed();
+    span.in_scope(|| match &result {
+        Ok(next) => info!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            "Next state: {}",
+            next.describe()
+        ),
+        Err(e) if is_go_back(e) => info!(elapsed_ms = elapsed.as_millis() as u64, "{}", e),
+        Err(e) => warn!(
+            elapsed_ms = elapsed.as_millis() as u64,
+            "Transition failed: {:#}", e
+        ),
+    });
+    if let (Some(path), Some(before)) = (&transition_log, before) {
+        let record = TransitionRecord {
+            at: Utc::now(),
+            state_type: std::any::type_name::<S>().to_string(),
+            before,
+            after: match &result {
+                Ok(next) => Some(serde_json::to_value(next)?),
+                Err(_) => None,
+            },
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            elapsed_ms: elapsed.as_millis(),
+        };
+        append_transition_record(path, &record).await?;
+    }
+    result
+}
+
+async fn append_transition_record(path: &PathBuf, record: &TransitionRecord) -> eyre::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .context(format!("Opening transition log {}", path.display()))?;
+    file.write_all(format!("{}\n", serde_json::to_string(record)?).as_bytes())
+        .await
+        .context(format!("Writing transition log {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::recovery::cancel_current_step;
+
+    #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+    enum SlowState {
+        #[default]
+        #[state(describe = "Wait")]
+        Wait,
+        #[state(describe = "Write", side_effects)]
+        Write,
+        #[state(describe = "Done", terminal)]
+        Done,
+    }
+    impl SlowState {
+        async fn transition(self) -> eyre::Result<Self> {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(Self::Done)
+        }
+    }
+
+    async fn step_cancelled_midway(state: SlowState) -> eyre::Result<SlowState> {
+        let cancel = tokio::spawn(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel_current_step();
+        });
+        let result = step(state).await;
+        cancel.await?;
+        result
+    }
+
+    #[tokio::test]
+    async fn cancelling_drops_a_step_without_side_effects() {
+        let result = step_cancelled_midway(SlowState::Wait).await;
+        assert!(result.is_err(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn cancelling_lets_a_step_with_side_effects_finish() -> eyre::Result<()> {
+        assert_eq!(
+            step_cancelled_midway(SlowState::Write).await?,
+            SlowState::Done
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use eyre::OptionExt;
+use eyre::bail;
+use ollama_rs::Ollama;
+use ollama_rs::generation::embeddings::request::EmbeddingsInput;
+use ollama_rs::generation::embeddings::request::GenerateEmbeddingsRequest;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::config::get_config;
+use crate::dictionary::Dictionary;
+use crate::generate::OllamaSettings;
+use crate::normalize::MatchOptions;
+use crate::search_index::tokenize;
+
+pub const EMBEDDING_BATCH_SIZE: usize = 32;
+
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifies the vectors this embedder produces, so a cache from another model is not reused.
+    fn model(&self) -> String;
+    async fn embed(&self, texts: &[String]) -> eyre::Result<Vec<Vec<f32>>>;
+}
+
+pub struct OllamaEmbedder {
+    pub settings: OllamaSettings,
+}
+
+impl OllamaEmbedder {
+    pub fn new(settings: OllamaSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Uses `ollama.url` and `ollama.embedding_model` from the config.
+    pub fn from_config() -> Self {
+        Self::new(OllamaSettings {
+            model: get_config().ollama.embedding_model.clone(),
+            ..OllamaSettings::from_config()
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for OllamaEmbedder {
+    fn model(&self) -> String {
+        format!("ollama:{}", self.settings.model)
+    }
+
+    async fn embed(&self, texts: &[String]) -> eyre::Result<Vec<Vec<f32>>> {
+        let (host, port) = self.settings.host_and_port()?;
+        let ollama = Ollama::new(host, port);
+        let response = ollama
+            .generate_embeddings(GenerateEmbeddingsRequest::new(
+                self.settings.model.clone(),
+                EmbeddingsInput::Multiple(texts.to_vec()),
+            ))
+            .await
+            .context(format!(
+                "Embedding {} texts with {} at {}",
+                texts.len(),
+                self.settings.model,
+                self.settings.url
+            ))?;
+        if response.embeddings.len() != texts.len() {
+            bail!(
+                "Asked for {} embeddings but got {}",
+                texts.len(),
+                response.embeddings.len()
+            );
+        }
+        Ok(response.embeddings)
+    }
+}
+
+/// Hashes each token into one of `dimensions` buckets, so texts sharing words point the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeEmbedder {
+    pub dimensions: usize,
+}
+
+impl Default for FakeEmbedder {
+    fn default() -> Self {
+        Self { dimensions: 64 }
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for FakeEmbedder {
+    fn model(&self) -> String {
+        format!("fake:{}", self.dimensions)
+    }
+
+    async fn embed(&self, texts: &[String]) -> eyre::Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let mut vector = vec![0.0; self.dimensions];
+                for token in tokenize(text, &MatchOptions::default()) {
+                    vector[(fnv1a(token.as_bytes()) % self.dimensions as u64) as usize] += 1.0;
+                }
+                vector
+            })
+            .collect())
+    }
+}
+
+/// A stable hash, unlike `DefaultHasher` which may change between Rust releases.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub fn get_definition_hash(definition: &str) -> String {
+    format!("{:016x}", fnv1a(definition.as_bytes()))
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Definition vectors keyed by definition hash, stored next to the dictionary.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct EmbeddingCache {
+    pub model: String,
+    pub vectors: HashMap<String, Vec<f32>>,
+}
+
+impl EmbeddingCache {
+    pub fn get_path(dictionary_path: impl AsRef<Path>) -> PathBuf {
+        dictionary_path.as_ref().with_extension("embeddings.json")
+    }
+
+    /// The cache at `path`, or an empty one if it is missing or was built by another model.
+    pub async fn load_or_default(path: impl AsRef<Path>, model: &str) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if !tokio::fs::try_exists(path).await? {
+            return Ok(Self {
+                model: model.to_string(),
+                ..Default::default()
+            });
+        }
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .context(format!("Reading {}", path.display()))?;
+        let cache: Self =
+            serde_json::from_str(&contents).context(format!("Parsing {}", path.display()))?;
+        if cache.model != model {
+            info!(
+                "Discarding embeddings from {} since the model is now {}",
+                cache.model, model
+            );
+            return Ok(Self {
+                model: model.to_string(),
+                ..Default::default()
+            });
+        }
+        Ok(cache)
+    }
+
+    pub async fn save(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        tokio::fs::write(path, serde_json::to_string(self)?)
+            .await
+            .context(format!("Writing {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// One vector per dictionary entry, embedding only definitions the cache hasn't seen.
+///
+/// Vectors for definitions no longer in the dictionary are dropped from the cache.
+pub async fn embed_dictionary(
+    dictionary: &Dictionary,
+    embedder: &dyn Embedder,
+    cache: &mut EmbeddingCache,
+) -> eyre::Result<Vec<Vec<f32>>> {
+    let hashes: Vec<String> = dictionary
+        .entries
+        .iter()
+        .map(|entry| get_definition_hash(&entry.definition))
+        .collect();
+    let mut seen: HashSet<&String> = HashSet::new();
+    let mut missing: Vec<(String, String)> = Vec::new();
+    for (hash, entry) in hashes.iter().zip(&dictionary.entries) {
+        if !cache.vectors.contains_key(hash) && seen.insert(hash) {
+            missing.push((hash.clone(), entry.definition.clone()));
+        }
+    }
+    if !missing.is_empty() {
+        info!(
+            "Embedding {} new or changed definitions with {}",
+            missing.len(),
+            embedder.model()
+        );
+    }
+    for batch in missing.chunks(EMBEDDING_BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = embedder.embed(&texts).await?;
+        if vectors.len() != batch.len() {
+            bail!(
+                "{} returned {} vectors for {} definitions",
+                embedder.model(),
+                vectors.len(),
+                batch.len()
+            );
+        }
+        for ((hash, _), vector) in batch.iter().zip(vectors) {
+            cache.vectors.insert(hash.clone(), vector);
+        }
+    }
+    let wanted: HashSet<&String> = hashes.iter().collect();
+    cache.vectors.retain(|hash, _| wanted.contains(hash));
+    hashes
+        .iter()
+        .map(|hash| {
+            cache
+                .vectors
+                .get(hash)
+                .cloned()
+                .ok_or_eyre(format!("No vector cached for definition {hash}"))
+        })
+        .collect()
+}
+
+/// The `limit` headwords whose best definition is closest to `query_vector`, closest first.
+pub fn nearest_words(
+    dictionary: &Dictionary,
+    vectors: &[Vec<f32>],
+    query_vector: &[f32],
+    limit: usize,
+) -> Vec<(String, f32)> {
+    let mut best: HashMap<String, f32> = HashMap::new();
+    for (entry, vector) in dictionary.entries.iter().zip(vectors) {
+        let similarity = cosine_similarity(query_vector, vector);
+        let score = best.entry(entry.word.clone()).or_insert(f32::MIN);
+        if similarity > *score {
+            *score = similarity;
+        }
+    }
+    let mut rtn: Vec<(String, f32)> = best.into_iter().collect();
+    rtn.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    rtn.truncate(limit);
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::DictionaryEntry;
+
+    fn entry(word: &str, definition: &str) -> DictionaryEntry {
+        DictionaryEntry {
+            word: word.to_string(),
+            definition: definition.to_string(),
+            source: "test".to_string(),
+        }
+    }
+
+    /// Drops the last vector of every batch, like a server that silently skips an input.
+    struct ShortEmbedder;
+
+    #[async_trait::async_trait]
+    impl Embedder for ShortEmbedder {
+        fn model(&self) -> String {
+            "short".to_string()
+        }
+
+        async fn embed(&self, texts: &[String]) -> eyre::Result<Vec<Vec<f32>>> {
+            let mut rtn = FakeEmbedder::default().embed(texts).await?;
+            rtn.pop();
+            Ok(rtn)
+        }
+    }
+
+    #[tokio::test]
+    async fn reverse_lookup_finds_the_word_for_a_description() -> eyre::Result<()> {
+        let dictionary = Dictionary::new(vec![
+            entry("dog", "a domesticated animal that barks"),
+            entry("cat", "a small animal that purrs and meows"),
+            entry("kettle", "a metal pot for boiling water"),
+        ]);
+        let embedder = FakeEmbedder::default();
+        let mut cache = EmbeddingCache::default();
+        let vectors = embed_dictionary(&dictionary, &embedder, &mut cache).await?;
+        assert_eq!(vectors.len(), 3);
+        assert_eq!(cache.vectors.len(), 3);
+
+        let query_vector = embedder
+            .embed(&["something for boiling water".to_string()])
+            .await?
+            .remove(0);
+        let nearest = nearest_words(&dictionary, &vectors, &query_vector, 2);
+        assert_eq!(nearest[0].0, "kettle");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn embed_dictionary_drops_vectors_for_removed_definitions() -> eyre::Result<()> {
+        let embedder = FakeEmbedder::default();
+        let mut cache = EmbeddingCache::default();
+        let before = Dictionary::new(vec![entry("dog", "barks"), entry("cat", "purrs")]);
+        embed_dictionary(&before, &embedder, &mut cache).await?;
+        let after = Dictionary::new(vec![entry("dog", "barks")]);
+        embed_dictionary(&after, &embedder, &mut cache).await?;
+        assert_eq!(
+            cache.vectors.keys().collect::<Vec<_>>(),
+            vec![&get_definition_hash("barks")]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn embed_dictionary_rejects_missing_vectors() {
+        let dictionary = Dictionary::new(vec![entry("dog", "barks"), entry("cat", "purrs")]);
+        let mut cache = EmbeddingCache::default();
+        let result = embed_dictionary(&dictionary, &ShortEmbedder, &mut cache).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::path::Component;
+use std::path::Path;
+
+use eyre::Context;
+use eyre::bail;
+use eyre::eyre;
+use ollama_rs::Ollama;
+use ollama_rs::generation::completion::request::GenerationRequest;
+use serde::Deserialize;
+use serde::Serialize;
+use similar::TextDiff;
+use tracing::info;
+
+use crate::cargo_diagnostics::DiagnosticsReport;
+use crate::config::get_config;
+
+/// Where to reach the model, from the `ollama` section of the config.
+///
+/// Pointing `ollama.url` (or `OLLAMA_HOST`) at another HTTP server lets the generation run
+/// without a real Ollama.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OllamaSettings {
+    pub url: String,
+    pub model: String,
+}
+
+impl OllamaSettings {
+    pub fn from_config() -> Self {
+        let ollama = &get_config().ollama;
+        Self {
+            url: ollama.url.clone(),
+            model: ollama.model.clone(),
+        }
+    }
+
+    /// Split the url into the host and port that `Ollama::new` wants.
+    pub fn host_and_port(&self) -> eyre::Result<(String, u16)> {
+        let url = if self.url.contains("://") {
+            self.url.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", self.url.trim_end_matches('/'))
+        };
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| eyre!("Ollama url {:?} has no scheme", self.url))?;
+        match rest.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse::<u16>()
+                    .context(format!("Parsing port in ollama url {:?}", self.url))?;
+                Ok((format!("{scheme}://{host}"), port))
+            }
+            None => Ok((url.clone(), 11434)),
+        }
+    }
+}
+
+/// A whole-file replacement proposed by the model, relative to the version directory.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProposedEdit {
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProposedEdits {
+    pub edits: Vec<ProposedEdit>,
+}
+
+/// The files the model gets to see, relative to the version directory.
+pub async fn read_version_sources(
+    version_dir: impl AsRef<Path>,
+) -> eyre::Result<Vec<(String, String)>> {
+    let version_dir = version_dir.as_ref();
+    let mut rtn = Vec::new();
+    for file in ["Cargo.toml", "next steps.md"] {
+        let path = version_dir.join(file);
+        if tokio::fs::try_exists(&path).await? {
+            rtn.push((file.to_string(), tokio::fs::read_to_string(&path).await?));
+        }
+    }
+    let mut entries = tokio::fs::read_dir(version_dir.join("src")).await?;
+    let mut sources = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|x| x == "rs") {
+            sources.push((
+                format!("src/{}", entry.file_name().to_string_lossy()),
+                tokio::fs::read_to_string(&path).await?,
+            ));
+        }
+    }
+    sources.sort();
+    rtn.extend(sources);
+    Ok(rtn)
+}
+
+pub fn build_generation_prompt(
+    version_name: &str,
+    files: &[(String, String)],
+    report: Option<&DiagnosticsReport>,
+) -> String {
+    let mut rtn = format!(
+        "You are helping write the next version of the Rust crate {version_name}.\n\
+         Work on the items in `next steps.md` and fix any build errors.\n\
+         Respond with only JSON of the form \
+         {{\"edits\": [{{\"path\": \"src/lib.rs\", \"contents\": \"<entire new file>\"}}]}}.\n\
+         Paths are relative to the crate root and each edit replaces the whole file.\n"
+    );
+    for (path, contents) in files {
+        rtn.push_str(&format!("\n=== {path} ===\n{contents}\n"));
+    }
+    if let Some(report) = report {
+        rtn.push_str(&format!("\n=== cargo build ===\n{}\n", report.summarize()));
+    }
+    rtn
+}
+
+/// Pull the edits out of the model response, tolerating prose or code fences around the JSON.
+pub fn parse_proposed_edits(response: &str) -> eyre::Result<Vec<ProposedEdit>> {
+    let (Some(start), Some(end)) = (response.find('{'), response.rfind('}')) else {
+        bail!("No JSON object in model response: {response:?}");
+    };
+    if end < start {
+        bail!("No JSON object in model response: {response:?}");
+    }
+    let proposed: ProposedEdits = serde_json::from_str(&response[start..=end])
+        .context("Parsing the model response as proposed edits")?;
+    for edit in &proposed.edits {
+        ensure_relative_path(&edit.path)?;
+    }
+    Ok(proposed.edits)
+}
+
+/// Refuse paths that would let an edit escape the version directory.
+pub fn ensure_relative_path(path: &str) -> eyre::Result<()> {
+    let is_contained = Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || !is_contained {
+        bail!("Proposed edit path {path:?} must be relative to the version directory");
+    }
+    Ok(())
+}
+
+pub async fn request_proposed_edits(
+    settings: &OllamaSettings,
+    prompt: String,
+) -> eyre::Result<Vec<ProposedEdit>> {
+    let (host, port) = settings.host_and_port()?;
+    info!(
+        "Asking {} at {}:{} for proposed edits",
+        settings.model, host, port
+    );
+    let ollama = Ollama::new(host, port);
+    let response = ollama
+        .generate(GenerationRequest::new(settings.model.clone(), prompt))
+        .await
+        .context(format!(
+            "Generating with {} at {}",
+            settings.model, settings.url
+        ))?;
+    parse_proposed_edits(&response.response)
+}
+
+pub fn render_diff(path: &str, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(&format!("a/{path}"), &format!("b/{path}"))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::Json;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+
+    use super::*;
+
+    async fn generate(
+        Json(request): Json<serde_json::Value>,
+    ) -> (StatusCode, Json<serde_json::Value>) {
+        if request["model"] != "stub-model" {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({})));
+        }
+        let edits = serde_json::json!({
+            "edits": [{"path": "src/main.rs", "contents": "fn main() {}\n"}]
+        });
+        let response = serde_json::json!({
+            "model": "stub-model",
+            "created_at": "2025-01-01T00:00:00Z",
+            "response": format!("Here you go:\n```json\n{edits}\n```"),
+            "done": true,
+        });
+        (StatusCode::OK, Json(response))
+    }
+
+    #[tokio::test]
+    async fn request_proposed_edits_parses_the_endpoint_response() -> eyre::Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let settings = OllamaSettings {
+            url: format!("http://{}", listener.local_addr()?),
+            model: "stub-model".to_string(),
+        };
+        let router = Router::new().route("/api/generate", post(generate));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let edits = request_proposed_edits(&settings, "Write the next version".to_string()).await?;
+        assert_eq!(
+            edits,
+            vec![ProposedEdit {
+                path: "src/main.rs".to_string(),
+                contents: "fn main() {}\n".to_string(),
+            }]
+        );
+        Ok(())
+    }
+}
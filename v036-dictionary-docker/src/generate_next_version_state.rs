@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::OptionExt;
+use itertools::Itertools;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::cargo_diagnostics::get_diagnostics_report_path;
+use crate::cargo_diagnostics::read_diagnostics_report;
+use crate::config::get_config;
+use crate::generate::OllamaSettings;
+use crate::generate::ProposedEdit;
+use crate::generate::build_generation_prompt;
+use crate::generate::ensure_relative_path;
+use crate::generate::read_version_sources;
+use crate::generate::render_diff;
+use crate::generate::request_proposed_edits;
+use crate::prompt::pick_answer;
+use crate::state::State;
+use crate::workspace::copy_version_as_unregistered_version;
+use crate::workspace::prompt_unregistered_version_name;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum GenerateNextVersionState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose template version")]
+    ChooseTemplateVersion { workspace_dir: PathBuf },
+    #[state(describe = "Request proposed edits from the model")]
+    RequestProposedEdits {
+        workspace_dir: PathBuf,
+        template_version_name: String,
+    },
+    #[state(describe = "Choose next version name")]
+    ChooseNextVersionName {
+        workspace_dir: PathBuf,
+        template_version_name: String,
+        proposed_edits: Vec<ProposedEdit>,
+    },
+    #[state(describe = "Copy template version", side_effects)]
+    CopyTemplateVersion {
+        workspace_dir: PathBuf,
+        template_version_name: String,
+        next_version_name: String,
+        proposed_edits: Vec<ProposedEdit>,
+    },
+    #[state(describe = "Review proposed edits")]
+    ReviewProposedEdits {
+        next_version_dir: PathBuf,
+        proposed_edits: Vec<ProposedEdit>,
+    },
+    #[state(describe = "Apply accepted edits", side_effects)]
+    ApplyAcceptedEdits {
+        next_version_dir: PathBuf,
+        accepted_edits: Vec<ProposedEdit>,
+    },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl GenerateNextVersionState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseTemplateVersion { workspace_dir })
+            }
+            Self::ChooseTemplateVersion { workspace_dir } => {
+                let mut choices = get_versions(&workspace_dir)
+                    .await?
+                    .into_iter()
+                    .filter(|version| version.join("src").exists())
+                    .map(|version| Choice {
+                        key: version.display().to_string(),
+                        value: version,
+                    })
+                    .collect_vec();
+                choices.reverse();
+                let config = get_config();
+                choices.sort_by_key(|choice| !config.is_default_template_version(&choice.value));
+                let chosen = pick_answer(FzfArgs {
+                    choices,
+                    header: Some("Choose a version to generate the next version from".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::RequestProposedEdits {
+                    workspace_dir,
+                    template_version_name: chosen
+                        .value
+                        .file_name()
+                        .ok_or_eyre(format!("No file name for {:?}", chosen.value))?
+                        .to_string_lossy()
+                        .to_string(),
+                })
+            }
+            Self::RequestProposedEdits {
+                workspace_dir,
+                template_version_name,
+            } => {
+                let files =
+                    read_version_sources(workspace_dir.join(&template_version_name)).await?;
+                let report_path =
+                    get_diagnostics_report_path(&workspace_dir, &template_version_name);
+                let report = if tokio::fs::try_exists(&report_path).await? {
+                    Some(read_diagnostics_report(&report_path).await?)
+                } else {
+                    info!(
+                        "No diagnostics captured for {}, run CaptureDiagnostics first to include them",
+                        template_version_name
+                    );
+                    None
+                };
+                let prompt =
+                    build_generation_prompt(&template_version_name, &files, report.as_ref());
+                let proposed_edits =
+                    request_proposed_edits(&OllamaSettings::from_config(), prompt).await?;
+                info!("The model proposed {} edits", proposed_edits.len());
+                Ok(Self::ChooseNextVersionName {
+                    workspace_dir,
+                    template_version_name,
+                    proposed_edits,
+                })
+            }
+            Self::ChooseNextVersionName {
+                workspace_dir,
+                template_version_name,
+                proposed_edits,
+            } => {
+                let next_version_name =
+                    prompt_unregistered_version_name(&workspace_dir, &template_version_name)
+                        .await?;
+                Ok(Self::CopyTemplateVersion {
+                    workspace_dir,
+                    template_version_name,
+                    next_version_name,
+                    proposed_edits,
+                })
+            }
+            Self::CopyTemplateVersion {
+                workspace_dir,
+                template_version_name,
+                next_version_name,
+                proposed_edits,
+            } => {
+                let next_version_dir = copy_version_as_unregistered_version(
+                    &workspace_dir,
+                    &template_version_name,
+                    &next_version_name,
+                )
+                .await?;
+                Ok(Self::ReviewProposedEdits {
+                    next_version_dir,
+                    proposed_edits,
+                })
+            }
+            Self::ReviewProposedEdits {
+                next_version_dir,
+                proposed_edits,
+            } => {
+                let mut accepted_edits = Vec::new();
+                for edit in proposed_edits {
+                    let path = next_version_dir.join(&edit.path);
+                    let old = if tokio::fs::try_exists(&path).await? {
+                        tokio::fs::read_to_string(&path).await?
+                    } else {
+                        String::new()
+                    };
+                    if old == edit.contents {
+                        info!("Skipping {} since it is unchanged", edit.path);
+                        continue;
+                    }
+                    info!("{}", render_diff(&edit.path, &old, &edit.contents));
+                    let accept =
+                        answer_opt(&format!("Apply this edit to {}?", edit.path), |prompt| {
+                            Ok(dialoguer::Confirm::new()
+                                .with_prompt(prompt)
+                                .interact_opt()?)
+                        })?;
+                    if accept {
+                        accepted_edits.push(edit);
+                    }
+                }
+                Ok(Self::ApplyAcceptedEdits {
+                    next_version_dir,
+                    accepted_edits,
+                })
+            }
+            Self::ApplyAcceptedEdits {
+                next_version_dir,
+                accepted_edits,
+            } => {
+                for edit in &accepted_edits {
+                    ensure_relative_path(&edit.path)?;
+                    let path = next_version_dir.join(&edit.path);
+                    if let Some(parent) = path.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    info!("Writing {}", path.display());
+                    tokio::fs::write(&path, &edit.contents).await?;
+                }
+                info!(
+                    "Applied {} edits to {}",
+                    accepted_edits.len(),
+                    next_version_dir.display()
+                );
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
@@ -1,24 +1,71 @@
 pub mod state;
 pub mod define_word_state;
 pub mod create_new_version_state;
+pub mod archive;
+pub mod archive_versions_state;
+pub mod restore_versions_state;
+pub mod workspace;
+pub mod promote;
+pub mod promote_shared_items_state;
+pub mod cargo_diagnostics;
+pub mod capture_diagnostics_state;
+pub mod generate;
+pub mod generate_next_version_state;
+pub mod command_runner;
+pub mod docker;
+pub mod dockerize_version_state;
+pub mod dictionary;
+pub mod server;
+pub mod cli;
+pub mod search_index;
+pub mod search_definitions_state;
+pub mod embeddings;
+pub mod reverse_lookup_state;
+pub mod batch_define;
+pub mod session;
+pub mod lookup_history;
+pub mod browse_lookups_state;
+pub mod scheduler;
+pub mod quiz;
+pub mod quiz_state;
+pub mod normalize;
+pub mod driver;
+pub mod prompt;
+pub mod replay;
+pub mod recovery;
+pub mod state_graph;
+pub mod config;
 
 pub async fn create_new_version() -> eyre::Result<()> {
     tracing::info!("Creating a new version");
-    v007_create_new_version::create_new_version().await?;
+    nanuak_core::create_new_version().await?;
     Ok(())
 }
 
-pub async fn define_word(_word: &str) -> eyre::Result<()> {
+pub async fn define_word(word: &str) -> eyre::Result<()> {
     tracing::info!("Defining a word");
-    let _definition = todo!("get word definition");
-    // println!("{}: {}", word, definition);
-    // Ok(())
+    let dictionary_path = dictionary::get_dictionary_path().await?;
+    let dictionary = dictionary::Dictionary::load(dictionary_path).await?;
+    let entries = dictionary.define(word);
+    lookup_history::append_lookup(&lookup_history::LookupRecord {
+        word: word.to_string(),
+        looked_up_at: chrono::Utc::now(),
+        found: !entries.is_empty(),
+    })
+    .await?;
+    if entries.is_empty() {
+        println!("{}: no definition found", word);
+    }
+    for entry in entries {
+        println!("{}: {} ({})", entry.word, entry.definition, entry.source);
+    }
+    Ok(())
 }
 
 pub async fn prompt_user_for_word() -> eyre::Result<String> {
     tracing::info!("Prompting the user for a word");
-    let word = dialoguer::Input::new()
-        .with_prompt("Enter a word")
-        .interact()?;
+    let word = nanuak_core::prompt::answer("Enter a word", |prompt| {
+        Ok(dialoguer::Input::new().with_prompt(prompt).interact()?)
+    })?;
     Ok(word)
 }
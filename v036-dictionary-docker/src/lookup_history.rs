@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Utc;
+use eyre::Context;
+use eyre::OptionExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+pub const FAVOURITES_FILE_NAME: &str = "favourites.json";
+
+static DATA_DIR_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Use `dir` as the data dir for the rest of the process, so a replay leaves the user's
+/// history, favourites, quiz progress and recovery checkpoint alone.
+pub fn redirect_data_dir(dir: impl Into<PathBuf>) {
+    *DATA_DIR_OVERRIDE.lock().unwrap() = Some(dir.into());
+}
+
+/// The dir given to `redirect_data_dir`, then `NANUAK_DATA_DIR` if set, otherwise `nanuak` in
+/// the user's data dir.
+pub fn get_data_dir() -> eyre::Result<PathBuf> {
+    if let Some(dir) = DATA_DIR_OVERRIDE.lock().unwrap().clone() {
+        return Ok(dir);
+    }
+    if let Ok(dir) = std::env::var("NANUAK_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    Ok(dirs::data_dir()
+        .ok_or_eyre("No data dir for the current user")?
+        .join("nanuak"))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LookupRecord {
+    pub word: String,
+    pub looked_up_at: DateTime<Utc>,
+    pub found: bool,
+}
+
+/// Add a line to the history file, creating the data dir on first use.
+pub async fn append_lookup(record: &LookupRecord) -> eyre::Result<()> {
+    let data_dir = get_data_dir()?;
+    tokio::fs::create_dir_all(&data_dir).await?;
+    let path = data_dir.join(HISTORY_FILE_NAME);
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .context(format!("Opening {}", path.display()))?;
+    file.write_all(format!("{}\n", serde_json::to_string(record)?).as_bytes())
+        .await?;
+    Ok(())
+}
+
+/// Every lookup, oldest first.
+pub async fn read_history() -> eyre::Result<Vec<LookupRecord>> {
+    let path = get_data_dir()?.join(HISTORY_FILE_NAME);
+    if !tokio::fs::try_exists(&path).await? {
+        return Ok(Vec::new());
+    }
+    let contents = tokio::fs::read_to_string(&path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).context(format!("Parsing {:?} in {}", line, path.display()))
+        })
+        .collect()
+}
+
+/// The latest lookup of each word, most recent first.
+pub fn get_latest_lookups(history: &[LookupRecord]) -> Vec<&LookupRecord> {
+    let mut rtn: Vec<&LookupRecord> = Vec::new();
+    for record in history.iter().rev() {
+        if !rtn.iter().any(|x| x.word == record.word) {
+            rtn.push(record);
+        }
+    }
+    rtn
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Favourite {
+    pub word: String,
+    pub starred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Favourites {
+    pub favourites: Vec<Favourite>,
+}
+
+impl Favourites {
+    pub async fn read() -> eyre::Result<Self> {
+        let path = get_data_dir()?.join(FAVOURITES_FILE_NAME);
+        if !tokio::fs::try_exists(&path).await? {
+            return Ok(Self::default());
+        }
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .context(format!("Reading {}", path.display()))?;
+        serde_json::from_str(&contents).context(format!("Parsing {}", path.display()))
+    }
+
+    pub async fn write(&self) -> eyre::Result<()> {
+        let data_dir = get_data_dir()?;
+        tokio::fs::create_dir_all(&data_dir).await?;
+        let path = data_dir.join(FAVOURITES_FILE_NAME);
+        tokio::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .await
+            .context(format!("Writing {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.favourites.iter().any(|x| x.word == word)
+    }
+
+    /// Star `word` if it isn't already, otherwise unstar it. Returns whether it is now starred.
+    pub fn toggle(&mut self, word: &str) -> bool {
+        if self.contains(word) {
+            self.favourites.retain(|x| x.word != word);
+            false
+        } else {
+            self.favourites.push(Favourite {
+                word: word.to_string(),
+                starred_at: Utc::now(),
+            });
+            true
+        }
+    }
+}
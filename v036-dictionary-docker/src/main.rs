@@ -1,17 +1,122 @@
-use v036_dictionary_docker::state::State;
+use clap::Parser;
+use nanuak_core::logging::init_logging;
+use nanuak_core::logging::install_error_hook;
+use nanuak_core::prompt::get_remaining_answer_count;
+use v036_dictionary_docker::batch_define::run_batch_define;
+use v036_dictionary_docker::cli::Cli;
+use v036_dictionary_docker::cli::Command;
+use v036_dictionary_docker::cli::ConfigCommand;
+use v036_dictionary_docker::config::get_resolved_config;
+use v036_dictionary_docker::config::load_config;
+use v036_dictionary_docker::config::set_config;
+use v036_dictionary_docker::dictionary::Dictionary;
+use v036_dictionary_docker::dictionary::get_dictionary_path;
+use v036_dictionary_docker::driver::DriverOptions;
+use v036_dictionary_docker::driver::drive_with_options;
+use v036_dictionary_docker::recovery::INTERRUPTED_EXIT_CODE;
+use v036_dictionary_docker::recovery::Interrupted;
+use v036_dictionary_docker::recovery::listen_for_interrupts;
+use v036_dictionary_docker::recovery::take_checkpoint_to_resume;
+use v036_dictionary_docker::replay::check_replay;
+use v036_dictionary_docker::replay::drive_replay;
+use v036_dictionary_docker::replay::start_recording;
+use v036_dictionary_docker::replay::start_replay;
+use v036_dictionary_docker::state::DictionaryApplicationState;
+use v036_dictionary_docker::state_graph::run_states_command;
 
 #[tokio::main]
 pub async fn main() -> eyre::Result<()> {
+    install_error_hook()?;
+    let cli = Cli::parse();
+    let config = load_config().await?;
+    // Outside a workspace there is nowhere for the log file, which shouldn't stop commands
+    // like `config show`, so log to stderr alone and say why once logging is up.
+    let (log_file_dir, log_file_error) = match config.config.get_log_file_dir(cli.log_file).await {
+        Ok(log_file_dir) => (log_file_dir, None),
+        Err(e) => (None, Some(e)),
+    };
+    let log_options = config
+        .config
+        .get_log_options(cli.get_verbosity(), log_file_dir)?;
+    init_logging(&log_options)?;
+    if let Some(e) = log_file_error {
+        tracing::warn!("Logging to stderr only: {:#}", e);
+    }
+    set_config(config);
     tracing::info!("Ahoy!");
-    v006_create_new_version::init().await?;
-    let mut state = v036_dictionary_docker::state::DictionaryApplicationState::default();
-    loop {
-        tracing::info!("Current state: {}", state.describe());
-        state = state.next().await?;
-        if state.is_terminal() {
-            break;
+    let replay = match &cli.command {
+        Some(Command::Replay {
+            recording,
+            workspace,
+        }) => Some(start_replay(recording, workspace.clone()).await?),
+        _ => None,
+    };
+    let transition_log = match &cli.record {
+        Some(recording_dir) if replay.is_none() => Some(start_recording(recording_dir).await?),
+        _ => cli.transition_log,
+    };
+    match cli.command {
+        Some(Command::Serve { addr, dictionary }) => {
+            let dictionary_path = match dictionary {
+                Some(path) => path,
+                None => get_dictionary_path().await?,
+            };
+            let dictionary = Dictionary::load(dictionary_path).await?;
+            v036_dictionary_docker::server::serve(addr, dictionary).await?;
+            return Ok(());
+        }
+        Some(Command::Define {
+            file,
+            format,
+            dictionary,
+        }) => {
+            let dictionary_path = match dictionary {
+                Some(path) => path,
+                None => get_dictionary_path().await?,
+            };
+            let dictionary = Dictionary::load(dictionary_path).await?;
+            run_batch_define(&dictionary, file.as_deref(), format).await?;
+            return Ok(());
+        }
+        Some(Command::States {
+            machine,
+            format,
+            check,
+        }) => {
+            run_states_command(machine, format, &check).await?;
+            return Ok(());
+        }
+        Some(Command::Config {
+            command: ConfigCommand::Show,
+        }) => {
+            print!("{}", get_resolved_config().render());
+            return Ok(());
+        }
+        Some(Command::Replay { .. }) | None => {}
+    }
+    let initial_state = match (&replay, &cli.record) {
+        (None, None) => take_checkpoint_to_resume().await?.unwrap_or_default(),
+        _ => DictionaryApplicationState::default(),
+    };
+    listen_for_interrupts();
+    let result = match &replay {
+        Some(replay) => drive_replay(replay).await,
+        None => drive_with_options(initial_state, DriverOptions { transition_log }).await,
+    };
+    if let Err(e) = &result
+        && let Some(interrupted) = e.downcast_ref::<Interrupted>()
+    {
+        tracing::warn!("{}", interrupted);
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    }
+    if let Some(replay) = replay {
+        let remaining = get_remaining_answer_count();
+        if remaining > 0 {
+            tracing::warn!("{} recorded answers were never asked for", remaining);
         }
+        check_replay(&replay).await?;
     }
+    result?;
     tracing::info!("Goodbye from {}", env!("CARGO_PKG_NAME"));
     Ok(())
 }
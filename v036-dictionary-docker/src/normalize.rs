@@ -0,0 +1,111 @@
+use serde::Deserialize;
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    #[serde(alias = "nfc")]
+    Nfc,
+    /// Also folds compatibility characters, like "ﬁ" to "fi" and fullwidth letters to ASCII.
+    #[default]
+    #[serde(alias = "nfkc")]
+    Nfkc,
+}
+
+/// How two strings are compared when looking up or searching words.
+///
+/// Only the comparison key is normalised; entries keep the text as written for display.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchOptions {
+    pub form: NormalizationForm,
+    /// Treat "café" and "cafe" as the same word.
+    pub ignore_diacritics: bool,
+}
+
+/// The key used to compare `text`: normalised, case folded and optionally without diacritics.
+pub fn normalize_for_matching(text: &str, options: &MatchOptions) -> String {
+    let normalized: String = match options.form {
+        NormalizationForm::Nfc => text.trim().nfc().collect(),
+        NormalizationForm::Nfkc => text.trim().nfkc().collect(),
+    };
+    // Case folding can produce decomposed sequences, so normalise again afterwards
+    let folded = caseless::default_case_fold_str(&normalized);
+    if options.ignore_diacritics {
+        folded
+            .nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect()
+    } else {
+        folded.nfc().collect()
+    }
+}
+
+/// Whether `c` belongs inside a word, counting combining marks so decomposed accents aren't split off.
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_index::tokenize;
+
+    fn key(text: &str) -> String {
+        normalize_for_matching(text, &MatchOptions::default())
+    }
+
+    fn loose_key(text: &str) -> String {
+        normalize_for_matching(
+            text,
+            &MatchOptions {
+                ignore_diacritics: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn inuktitut_syllabics_are_kept_as_written() {
+        assert_eq!(key(" ᐃᓄᒃᑎᑐᑦ "), "ᐃᓄᒃᑎᑐᑦ");
+        // Long vowels are their own code points rather than a base plus a mark
+        assert_ne!(loose_key("ᐄᓄᒃ"), loose_key("ᐃᓄᒃ"));
+        assert!("ᐃᓄᒃᑎᑐᑦ".chars().all(is_word_char));
+    }
+
+    #[test]
+    fn syllabics_split_from_latin_on_punctuation_only() {
+        assert_eq!(
+            tokenize("ᐃᓄᒃᑎᑐᑦ (Inuktitut), ᐃᓄᐃᑦ's", &MatchOptions::default()),
+            vec!["ᐃᓄᒃᑎᑐᑦ", "inuktitut", "ᐃᓄᐃᑦ", "s"]
+        );
+    }
+
+    #[test]
+    fn case_folds_across_scripts() {
+        assert_eq!(key("ὈΔΌΣ"), key("ὀδός"));
+        assert_eq!(key("ЁЛКА"), key("ёлка"));
+        assert_eq!(key("Straße"), key("STRASSE"));
+    }
+
+    #[test]
+    fn nfkc_folds_compatibility_forms() {
+        assert_eq!(key("ｶﾀｶﾅ"), key("カタカナ"));
+        assert_eq!(key("ﬁle"), key("file"));
+        assert_eq!(key("ＡＢＣ"), "abc");
+        let nfc = MatchOptions {
+            form: NormalizationForm::Nfc,
+            ..Default::default()
+        };
+        assert_eq!(normalize_for_matching("ＡＢＣ", &nfc), "ａｂｃ");
+    }
+
+    #[test]
+    fn decomposed_and_composed_accents_match() {
+        assert_eq!(key("cafe\u{301}"), key("café"));
+        assert_ne!(key("café"), key("cafe"));
+        assert_eq!(loose_key("café"), loose_key("cafe"));
+        assert_eq!(loose_key("ёлка"), loose_key("елка"));
+    }
+}
@@ -0,0 +1,581 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use eyre::OptionExt;
+use eyre::bail;
+use itertools::Itertools;
+use nanuak_core::cargo_toml;
+use nanuak_core::version_name::VersionName;
+use syn::spanned::Spanned;
+use tracing::info;
+
+use crate::workspace::read_workspace_cargo_toml;
+use crate::workspace::write_workspace_cargo_toml;
+
+/// Name of the stable, non-versioned crate that shared items are promoted into.
+pub const DEFAULT_SHARED_CRATE_NAME: &str = "nanuak-core";
+
+/// Convert a package name like `v006-create-new-version` into the identifier used in paths.
+pub fn get_crate_ident(package_name: &str) -> String {
+    package_name.replace("-", "_")
+}
+
+/// A top-level public item from a crate's `lib.rs` along with its exact source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicItem {
+    pub name: String,
+    pub source: String,
+}
+
+/// A top-level `use` declaration from a crate's `lib.rs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseDeclaration {
+    /// The name the declaration brings into scope, or `*` for globs.
+    pub imported_name: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedLib {
+    pub uses: Vec<UseDeclaration>,
+    pub items: Vec<PublicItem>,
+}
+
+fn get_item_ident(item: &syn::Item) -> Option<(&syn::Visibility, &syn::Ident)> {
+    match item {
+        syn::Item::Fn(x) => Some((&x.vis, &x.sig.ident)),
+        syn::Item::Mod(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Struct(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Enum(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Trait(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Const(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Static(x) => Some((&x.vis, &x.ident)),
+        syn::Item::Type(x) => Some((&x.vis, &x.ident)),
+        _ => None,
+    }
+}
+
+fn get_imported_name(tree: &syn::UseTree) -> String {
+    match tree {
+        syn::UseTree::Path(x) => get_imported_name(&x.tree),
+        syn::UseTree::Name(x) => x.ident.to_string(),
+        syn::UseTree::Rename(x) => x.rename.to_string(),
+        syn::UseTree::Glob(_) | syn::UseTree::Group(_) => "*".to_string(),
+    }
+}
+
+fn get_source_text(lines: &[&str], span: proc_macro2::Span) -> String {
+    lines[span.start().line - 1..span.end().line].join("\n")
+}
+
+pub fn parse_lib(source: &str) -> eyre::Result<ParsedLib> {
+    let file = syn::parse_file(source)?;
+    let lines = source.lines().collect_vec();
+    let mut rtn = ParsedLib::default();
+    for item in &file.items {
+        if let syn::Item::Use(x) = item {
+            rtn.uses.push(UseDeclaration {
+                imported_name: get_imported_name(&x.tree),
+                source: get_source_text(&lines, item.span()),
+            });
+        } else if let Some((syn::Visibility::Public(_), ident)) = get_item_ident(item) {
+            rtn.items.push(PublicItem {
+                name: ident.to_string(),
+                source: get_source_text(&lines, item.span()),
+            });
+        }
+    }
+    Ok(rtn)
+}
+
+pub async fn get_public_items(crate_dir: impl AsRef<Path>) -> eyre::Result<Vec<PublicItem>> {
+    let lib_rs_path = crate_dir.as_ref().join("src").join("lib.rs");
+    let lib_rs = tokio::fs::read_to_string(&lib_rs_path)
+        .await
+        .context(format!("Reading {}", lib_rs_path.display()))?;
+    Ok(parse_lib(&lib_rs)
+        .context(format!("Parsing {}", lib_rs_path.display()))?
+        .items)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_promoted_path(path: &str, items: &[String]) -> bool {
+    items.iter().any(|item| {
+        path.strip_prefix(item.as_str())
+            .is_some_and(|x| !x.starts_with(is_ident_char))
+    })
+}
+
+/// Rewrite the group in `from::{a, b}` to take the promoted entries from `to`, or `None` if none are promoted.
+///
+/// Mixed groups become `{to::{a}, from::{b}}`, which is still a valid `use` tree.
+fn rewrite_group(group: &str, from: &str, to: &str, items: &[String]) -> Option<String> {
+    let (promoted, kept): (Vec<&str>, Vec<&str>) = group
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .partition(|x| is_promoted_path(x, items));
+    match (promoted.is_empty(), kept.is_empty()) {
+        (true, _) => None,
+        (false, true) => Some(format!("{to}::{{{}}}", promoted.join(", "))),
+        (false, false) => Some(format!(
+            "{{{to}::{{{}}}, {from}::{{{}}}}}",
+            promoted.join(", "),
+            kept.join(", ")
+        )),
+    }
+}
+
+/// Replace every `from::item` path with `to::item` for each of `items`, respecting identifier boundaries.
+///
+/// Grouped imports like `use from::{a, b};` are split when only some of their entries are promoted.
+/// Groups nested inside the group are left alone.
+pub fn rewrite_item_paths(source: &str, from: &str, to: &str, items: &[String]) -> String {
+    let mut rtn = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(index) = rest.find(from) {
+        let (before, after) = rest.split_at(index);
+        rtn.push_str(before);
+        rest = &after[from.len()..];
+        if rtn.chars().next_back().is_some_and(is_ident_char) {
+            rtn.push_str(from);
+            continue;
+        }
+        let Some(path) = rest.strip_prefix("::") else {
+            rtn.push_str(from);
+            continue;
+        };
+        if let Some(group) = path.strip_prefix('{') {
+            let rewritten = group
+                .find('}')
+                .filter(|end| !group[..*end].contains('{'))
+                .and_then(|end| Some((end, rewrite_group(&group[..end], from, to, items)?)));
+            match rewritten {
+                Some((end, rewritten)) => {
+                    rtn.push_str(&rewritten);
+                    rest = &group[end + 1..];
+                }
+                None => rtn.push_str(from),
+            }
+        } else if is_promoted_path(path, items) {
+            rtn.push_str(to);
+        } else {
+            rtn.push_str(from);
+        }
+    }
+    rtn.push_str(rest);
+    rtn
+}
+
+/// Member manifests are parsed loosely so sections CargoToml doesn't model survive the rewrite.
+async fn read_cargo_toml(path: &Path) -> eyre::Result<toml::Table> {
+    let cargo_toml = tokio::fs::read_to_string(path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    toml::from_str(&cargo_toml).context(format!("Parsing {}", path.display()))
+}
+
+async fn write_cargo_toml(path: &Path, cargo_toml: &toml::Table) -> eyre::Result<()> {
+    tokio::fs::write(path, toml::to_string(cargo_toml)?).await?;
+    Ok(())
+}
+
+fn get_dependencies_mut(cargo_toml: &mut toml::Table) -> eyre::Result<&mut toml::Table> {
+    cargo_toml
+        .entry("dependencies")
+        .or_insert_with(|| toml::Value::Table(Default::default()))
+        .as_table_mut()
+        .ok_or_eyre("Expected \"dependencies\" to be a table")
+}
+
+fn is_path_dependency(dependency: &toml::Value) -> bool {
+    dependency.get("path").is_some()
+}
+
+fn get_path_dependency(path: String) -> toml::Value {
+    toml::Value::Table(toml::Table::from_iter([(
+        "path".to_string(),
+        toml::Value::String(path),
+    )]))
+}
+
+/// Copy the named public items from `source_crate_name` into `shared_crate_name`, creating and registering the shared crate if needed.
+pub async fn promote_items(
+    workspace_dir: &Path,
+    source_crate_name: &str,
+    item_names: &[String],
+    shared_crate_name: &str,
+) -> eyre::Result<()> {
+    let source_dir = workspace_dir.join(source_crate_name);
+    let shared_dir = workspace_dir.join(shared_crate_name);
+    let shared_lib_rs_path = shared_dir.join("src").join("lib.rs");
+
+    let source_lib_rs = tokio::fs::read_to_string(source_dir.join("src").join("lib.rs")).await?;
+    let source_lib = parse_lib(&source_lib_rs)?;
+    let shared_lib_rs = if tokio::fs::try_exists(&shared_lib_rs_path).await? {
+        tokio::fs::read_to_string(&shared_lib_rs_path).await?
+    } else {
+        info!("Creating shared crate {}", shared_dir.display());
+        tokio::fs::create_dir_all(shared_dir.join("src")).await?;
+        for file_name in ["rustfmt.toml", ".gitignore"] {
+            let path = source_dir.join(file_name);
+            if tokio::fs::try_exists(&path).await? {
+                tokio::fs::copy(&path, shared_dir.join(file_name)).await?;
+            }
+        }
+        String::new()
+    };
+    let shared_lib = parse_lib(&shared_lib_rs)?;
+
+    let known_items = shared_lib
+        .items
+        .iter()
+        .map(|item| item.name.clone())
+        .chain(item_names.iter().cloned())
+        .collect_vec();
+    let mut promoted = Vec::new();
+    for name in item_names {
+        if shared_lib.items.iter().any(|item| &item.name == name) {
+            bail!("{} already exists in {}", name, shared_crate_name);
+        }
+        let item = source_lib
+            .items
+            .iter()
+            .find(|item| &item.name == name)
+            .ok_or_eyre(format!("No public item {name} in {source_crate_name}"))?;
+        promoted.push(item.source.clone());
+    }
+    let mut promoted = promoted.join("\n\n");
+
+    let source_cargo_toml = read_cargo_toml(&source_dir.join("Cargo.toml")).await?;
+    info!("Items that already live in the shared crate are referenced through crate::");
+    let source_dependencies = source_cargo_toml
+        .get("dependencies")
+        .and_then(|x| x.as_table())
+        .cloned()
+        .unwrap_or_default();
+    for (name, dependency) in &source_dependencies {
+        if is_path_dependency(dependency) {
+            promoted = rewrite_item_paths(&promoted, &get_crate_ident(name), "crate", &known_items);
+        }
+    }
+    let mut uses = shared_lib
+        .uses
+        .iter()
+        .map(|x| x.source.clone())
+        .collect_vec();
+    for declaration in &source_lib.uses {
+        // Imports are kept even if they look unused since trait imports are only referenced by their methods
+        if known_items.contains(&declaration.imported_name) {
+            continue;
+        }
+        if !uses.contains(&declaration.source) {
+            uses.push(declaration.source.clone());
+        }
+    }
+
+    let mut body = shared_lib_rs.clone();
+    for declaration in &shared_lib.uses {
+        body = body.replacen(&declaration.source, "", 1);
+    }
+    let mut body = body.trim().to_string();
+    if !body.is_empty() {
+        body.push_str("\n\n");
+    }
+    body.push_str(&promoted);
+    let shared_lib_rs = format!("{}\n\n{}\n", uses.iter().sorted().join("\n"), body);
+    tokio::fs::write(&shared_lib_rs_path, shared_lib_rs.trim_start()).await?;
+
+    info!("Carry over the dependencies of {}", source_crate_name);
+    let shared_cargo_toml_path = shared_dir.join("Cargo.toml");
+    let mut shared_cargo_toml = if tokio::fs::try_exists(&shared_cargo_toml_path).await? {
+        read_cargo_toml(&shared_cargo_toml_path).await?
+    } else {
+        let edition = source_cargo_toml
+            .get("package")
+            .and_then(|x| x.get("edition"))
+            .cloned()
+            .ok_or_eyre(format!(
+                "No package edition in {source_crate_name}/Cargo.toml"
+            ))?;
+        let package = toml::Table::from_iter([
+            (
+                "name".to_string(),
+                toml::Value::String(shared_crate_name.to_string()),
+            ),
+            (
+                "version".to_string(),
+                toml::Value::String("0.1.0".to_string()),
+            ),
+            ("edition".to_string(), edition),
+        ]);
+        toml::Table::from_iter([("package".to_string(), toml::Value::Table(package))])
+    };
+    let shared_dependencies = get_dependencies_mut(&mut shared_cargo_toml)?;
+    for (name, dependency) in source_dependencies {
+        shared_dependencies.entry(name).or_insert(dependency);
+    }
+    let lib_rs = tokio::fs::read_to_string(&shared_lib_rs_path).await?;
+    shared_dependencies.retain(|name, dependency| {
+        !is_path_dependency(dependency) || lib_rs.contains(&format!("{}::", get_crate_ident(name)))
+    });
+    write_cargo_toml(&shared_cargo_toml_path, &shared_cargo_toml).await?;
+
+    let mut workspace_cargo_toml = read_workspace_cargo_toml(workspace_dir).await?;
+    let workspace = workspace_cargo_toml
+        .workspace
+        .as_mut()
+        .ok_or_eyre("No workspace")?;
+    if !workspace.members.iter().any(|x| x == shared_crate_name) {
+        info!("Register {} in the workspace", shared_crate_name);
+        workspace.members.push(shared_crate_name.to_string());
+        workspace.dependencies.0.insert(
+            shared_crate_name.to_string(),
+            cargo_toml::Dependency::Path {
+                path: shared_crate_name.to_string(),
+                features: None,
+            },
+        );
+        write_workspace_cargo_toml(workspace_dir, &workspace_cargo_toml).await?;
+    }
+    Ok(())
+}
+
+async fn get_rust_files(dir: PathBuf) -> eyre::Result<Vec<PathBuf>> {
+    let mut rtn = Vec::new();
+    let mut pending = vec![dir];
+    while let Some(dir) = pending.pop() {
+        let mut children = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = children.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|x| x == "rs") {
+                rtn.push(path);
+            }
+        }
+    }
+    rtn.sort();
+    Ok(rtn)
+}
+
+/// The workspace members, other than the source and shared crates, whose code refers to the source crate.
+pub async fn get_dependents(
+    workspace_dir: &Path,
+    source_crate_name: &str,
+    shared_crate_name: &str,
+) -> eyre::Result<Vec<String>> {
+    let source_path = format!("{}::", get_crate_ident(source_crate_name));
+    let workspace_cargo_toml = read_workspace_cargo_toml(workspace_dir).await?;
+    let members = workspace_cargo_toml
+        .workspace
+        .ok_or_eyre("No workspace")?
+        .members;
+    let mut rtn = Vec::new();
+    for member in members
+        .into_iter()
+        .filter(|x| x != source_crate_name && x != shared_crate_name)
+    {
+        for path in get_rust_files(workspace_dir.join(&member).join("src")).await? {
+            if tokio::fs::read_to_string(&path)
+                .await?
+                .contains(&source_path)
+            {
+                rtn.push(member);
+                break;
+            }
+        }
+    }
+    Ok(rtn)
+}
+
+/// The newest versioned dependent, the only one rewritten unless others are chosen
+/// since older versions are kept pinned to the code they were written against.
+pub fn get_default_dependent(dependents: &[String]) -> Option<&String> {
+    dependents
+        .iter()
+        .filter_map(|x| Some((x.parse::<VersionName>().ok()?, x)))
+        .max()
+        .map(|(_, x)| x)
+}
+
+/// Point the chosen workspace members that used the promoted items at the shared crate instead, returning the members changed.
+pub async fn rewrite_dependents(
+    workspace_dir: &Path,
+    source_crate_name: &str,
+    item_names: &[String],
+    shared_crate_name: &str,
+    dependent_names: &[String],
+) -> eyre::Result<Vec<String>> {
+    let source_ident = get_crate_ident(source_crate_name);
+    let shared_ident = get_crate_ident(shared_crate_name);
+    let workspace_cargo_toml = read_workspace_cargo_toml(workspace_dir).await?;
+    let members = workspace_cargo_toml
+        .workspace
+        .ok_or_eyre("No workspace")?
+        .members;
+    if let Some(name) = dependent_names.iter().find(|x| !members.contains(x)) {
+        bail!("{} is not a workspace member", name);
+    }
+
+    let mut rewritten = Vec::new();
+    for member in members.iter().filter(|x| {
+        dependent_names.contains(x) && *x != source_crate_name && *x != shared_crate_name
+    }) {
+        let member_dir = workspace_dir.join(member);
+        let mut changed = false;
+        let mut still_uses_source = false;
+        for path in get_rust_files(member_dir.join("src")).await? {
+            let content = tokio::fs::read_to_string(&path).await?;
+            let new_content =
+                rewrite_item_paths(&content, &source_ident, &shared_ident, item_names);
+            if new_content != content {
+                info!("Rewrote paths in {}", path.display());
+                tokio::fs::write(&path, &new_content).await?;
+                changed = true;
+            }
+            still_uses_source |= new_content.contains(&format!("{source_ident}::"));
+        }
+        if !changed {
+            continue;
+        }
+
+        let cargo_toml_path = member_dir.join("Cargo.toml");
+        let mut cargo_toml = read_cargo_toml(&cargo_toml_path).await?;
+        let dependencies = get_dependencies_mut(&mut cargo_toml)?;
+        dependencies
+            .entry(shared_crate_name)
+            .or_insert_with(|| get_path_dependency(format!("../{shared_crate_name}")));
+        if !still_uses_source {
+            info!(
+                "{} no longer uses {}, removing the dependency",
+                member, source_crate_name
+            );
+            dependencies.remove(source_crate_name);
+        }
+        write_cargo_toml(&cargo_toml_path, &cargo_toml).await?;
+        rewritten.push(member.clone());
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<String> {
+        vec!["get_versions".to_string(), "cargo_toml".to_string()]
+    }
+
+    #[test]
+    fn rewrites_only_promoted_paths() {
+        let source = "use old::get_versions;\nuse old::get_versions_sorted;\nlet x = old::cargo_toml::CargoToml::default();\nbold::get_versions();\n";
+        assert_eq!(
+            rewrite_item_paths(source, "old", "new", &items()),
+            "use new::get_versions;\nuse old::get_versions_sorted;\nlet x = new::cargo_toml::CargoToml::default();\nbold::get_versions();\n"
+        );
+    }
+
+    #[test]
+    fn rewrites_grouped_imports() {
+        assert_eq!(
+            rewrite_item_paths(
+                "use old::{get_versions, cargo_toml::Dependency};",
+                "old",
+                "new",
+                &items()
+            ),
+            "use new::{get_versions, cargo_toml::Dependency};"
+        );
+        assert_eq!(
+            rewrite_item_paths(
+                "use old::{copy_dir_all, get_versions};",
+                "old",
+                "new",
+                &items()
+            ),
+            "use {new::{get_versions}, old::{copy_dir_all}};"
+        );
+        assert_eq!(
+            rewrite_item_paths("use old::{copy_dir_all, init};", "old", "new", &items()),
+            "use old::{copy_dir_all, init};"
+        );
+    }
+
+    #[test]
+    fn defaults_to_the_newest_versioned_dependent() {
+        let dependents = vec![
+            "v035-dictionary".to_string(),
+            "v1000-next".to_string(),
+            "nanuak-tools".to_string(),
+            "v999-old".to_string(),
+        ];
+        assert_eq!(
+            get_default_dependent(&dependents),
+            Some(&"v1000-next".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn promotes_between_crates_with_dev_dependencies() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace_dir = dir.path();
+        tokio::fs::write(
+            workspace_dir.join("Cargo.toml"),
+            "[workspace]\nresolver = \"2\"\nmembers = [\"v001-old\", \"v002-user\"]\n\n[workspace.dependencies]\n",
+        )
+        .await?;
+        for (name, dependencies, source) in [
+            ("v001-old", "", "pub fn get_versions() {}\n"),
+            (
+                "v002-user",
+                "v001-old = { path = \"../v001-old\" }\n",
+                "use v001_old::get_versions;\n",
+            ),
+        ] {
+            tokio::fs::create_dir_all(workspace_dir.join(name).join("src")).await?;
+            tokio::fs::write(
+                workspace_dir.join(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\n{dependencies}\n[dev-dependencies]\ntempfile = \"3.16.0\"\n"
+                ),
+            )
+            .await?;
+            tokio::fs::write(workspace_dir.join(name).join("src/lib.rs"), source).await?;
+        }
+
+        let items = vec!["get_versions".to_string()];
+        promote_items(workspace_dir, "v001-old", &items, "nanuak-shared").await?;
+        let rewritten = rewrite_dependents(
+            workspace_dir,
+            "v001-old",
+            &items,
+            "nanuak-shared",
+            &["v002-user".to_string()],
+        )
+        .await?;
+        assert_eq!(rewritten, vec!["v002-user".to_string()]);
+
+        let cargo_toml = read_cargo_toml(&workspace_dir.join("v002-user/Cargo.toml")).await?;
+        assert_eq!(
+            cargo_toml["dependencies"]["nanuak-shared"]["path"].as_str(),
+            Some("../nanuak-shared")
+        );
+        assert!(cargo_toml["dependencies"].get("v001-old").is_none());
+        assert_eq!(
+            cargo_toml["dev-dependencies"]["tempfile"].as_str(),
+            Some("3.16.0")
+        );
+        let shared_cargo_toml =
+            read_cargo_toml(&workspace_dir.join("nanuak-shared/Cargo.toml")).await?;
+        assert_eq!(
+            shared_cargo_toml["package"]["edition"].as_str(),
+            Some("2024")
+        );
+        Ok(())
+    }
+}
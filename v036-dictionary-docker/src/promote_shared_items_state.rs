@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::OptionExt;
+use eyre::bail;
+use itertools::Itertools;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::get_versions;
+use nanuak_core::prompt::answer;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::promote::DEFAULT_SHARED_CRATE_NAME;
+use crate::promote::get_default_dependent;
+use crate::promote::get_dependents;
+use crate::promote::get_public_items;
+use crate::promote::promote_items;
+use crate::promote::rewrite_dependents;
+use crate::prompt::pick_answer;
+use crate::state::State;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum PromoteSharedItemsState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose version to promote items from")]
+    ChooseSourceVersion { workspace_dir: PathBuf },
+    #[state(describe = "Choose items to promote")]
+    ChooseItems {
+        workspace_dir: PathBuf,
+        source_version_name: String,
+    },
+    #[state(describe = "Choose shared crate name")]
+    ChooseSharedCrateName {
+        workspace_dir: PathBuf,
+        source_version_name: String,
+        item_names: Vec<String>,
+    },
+    #[state(describe = "Choose dependents to rewrite")]
+    ChooseDependents {
+        workspace_dir: PathBuf,
+        source_version_name: String,
+        item_names: Vec<String>,
+        shared_crate_name: String,
+    },
+    #[state(describe = "Promote items", side_effects)]
+    PromoteItems {
+        workspace_dir: PathBuf,
+        source_version_name: String,
+        item_names: Vec<String>,
+        shared_crate_name: String,
+        dependent_names: Vec<String>,
+    },
+    #[state(describe = "Rewrite dependents", side_effects)]
+    RewriteDependents {
+        workspace_dir: PathBuf,
+        source_version_name: String,
+        item_names: Vec<String>,
+        shared_crate_name: String,
+        dependent_names: Vec<String>,
+    },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl PromoteSharedItemsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseSourceVersion { workspace_dir })
+            }
+            Self::ChooseSourceVersion { workspace_dir } => {
+                let versions = get_versions(&workspace_dir)
+                    .await?
+                    .into_iter()
+                    .filter(|version| version.join("src").join("lib.rs").exists())
+                    .map(|version| Choice {
+                        key: version.display().to_string(),
+                        value: version,
+                    })
+                    .collect_vec();
+                let chosen = pick_answer(FzfArgs {
+                    choices: versions,
+                    header: Some("Choose a version to promote items from".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::ChooseItems {
+                    workspace_dir,
+                    source_version_name: chosen
+                        .value
+                        .file_name()
+                        .ok_or_eyre(format!("No file name for {:?}", chosen.value))?
+                        .to_string_lossy()
+                        .to_string(),
+                })
+            }
+            Self::ChooseItems {
+                workspace_dir,
+                source_version_name,
+            } => {
+                let items = get_public_items(workspace_dir.join(&source_version_name)).await?;
+                let item_names = items.into_iter().map(|item| item.name).collect_vec();
+                let chosen = answer_opt(
+                    &format!("Choose the items to promote out of {source_version_name}"),
+                    |prompt| {
+                        Ok(dialoguer::MultiSelect::new()
+                            .with_prompt(prompt)
+                            .items(&item_names)
+                            .interact_opt()?)
+                    },
+                )?;
+                if chosen.is_empty() {
+                    bail!("No items chosen to promote");
+                }
+                Ok(Self::ChooseSharedCrateName {
+                    workspace_dir,
+                    source_version_name,
+                    item_names: chosen.into_iter().map(|i| item_names[i].clone()).collect(),
+                })
+            }
+            Self::ChooseSharedCrateName {
+                workspace_dir,
+                source_version_name,
+                item_names,
+            } => {
+                let shared_crate_name = answer("Enter the name of the shared crate", |prompt| {
+                    Ok(dialoguer::Input::<String>::new()
+                        .with_prompt(prompt)
+                        .default(DEFAULT_SHARED_CRATE_NAME.to_string())
+                        .interact()?)
+                })?;
+                Ok(Self::ChooseDependents {
+                    workspace_dir,
+                    source_version_name,
+                    item_names,
+                    shared_crate_name,
+                })
+            }
+            Self::ChooseDependents {
+                workspace_dir,
+                source_version_name,
+                item_names,
+                shared_crate_name,
+            } => {
+                let dependents =
+                    get_dependents(&workspace_dir, &source_version_name, &shared_crate_name)
+                        .await?;
+                let default_dependent = get_default_dependent(&dependents);
+                let defaults = dependents
+                    .iter()
+                    .map(|x| Some(x) == default_dependent)
+                    .collect_vec();
+                info!(
+                    "Versions left unchosen stay pinned to {}",
+                    source_version_name
+                );
+                let chosen = answer_opt(
+                    &format!("Choose the versions to point at {shared_crate_name}"),
+                    |prompt| {
+                        Ok(dialoguer::MultiSelect::new()
+                            .with_prompt(prompt)
+                            .items(&dependents)
+                            .defaults(&defaults)
+                            .interact_opt()?)
+                    },
+                )?;
+                Ok(Self::PromoteItems {
+                    workspace_dir,
+                    source_version_name,
+                    item_names,
+                    shared_crate_name,
+                    dependent_names: chosen.into_iter().map(|i| dependents[i].clone()).collect(),
+                })
+            }
+            Self::PromoteItems {
+                workspace_dir,
+                source_version_name,
+                item_names,
+                shared_crate_name,
+                dependent_names,
+            } => {
+                info!(
+                    "Promote {:?} from {} into {}",
+                    item_names, source_version_name, shared_crate_name
+                );
+                promote_items(
+                    &workspace_dir,
+                    &source_version_name,
+                    &item_names,
+                    &shared_crate_name,
+                )
+                .await?;
+                Ok(Self::RewriteDependents {
+                    workspace_dir,
+                    source_version_name,
+                    item_names,
+                    shared_crate_name,
+                    dependent_names,
+                })
+            }
+            Self::RewriteDependents {
+                workspace_dir,
+                source_version_name,
+                item_names,
+                shared_crate_name,
+                dependent_names,
+            } => {
+                let rewritten = rewrite_dependents(
+                    &workspace_dir,
+                    &source_version_name,
+                    &item_names,
+                    &shared_crate_name,
+                    &dependent_names,
+                )
+                .await?;
+                info!(
+                    "Pointed {:?} at {} instead of {}",
+                    rewritten, shared_crate_name, source_version_name
+                );
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
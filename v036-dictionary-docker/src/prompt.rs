@@ -0,0 +1,58 @@
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use cloud_terrastodon_core_user_input::prelude::pick;
+use eyre::OptionExt;
+use nanuak_core::prompt::GoBack;
+use nanuak_core::prompt::answer;
+use nanuak_core::prompt::is_back_allowed;
+
+/// `pick` through `nanuak_core::prompt::answer`, so the choice can be recorded and replayed.
+///
+/// The key of the chosen item is what gets recorded, keeping recordings readable.
+/// A "Back" choice is added when the driver has somewhere to go back to.
+pub fn pick_answer<T>(args: FzfArgs<Choice<T>>) -> eyre::Result<Choice<T>> {
+    let FzfArgs {
+        mut choices,
+        header,
+        prompt,
+    } = args;
+    let label = header
+        .clone()
+        .or_else(|| prompt.clone())
+        .unwrap_or_else(|| "Pick one".to_string());
+    let keys = choices
+        .iter()
+        .map(|choice| choice.key.clone())
+        .collect::<Vec<_>>();
+    let key = answer(&label, |_| {
+        let mut back_choices = Vec::new();
+        if is_back_allowed() {
+            back_choices.push(Choice {
+                key: "Back".to_string(),
+                value: None,
+            });
+        }
+        let chosen = pick(FzfArgs {
+            choices: keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| Choice {
+                    key: key.clone(),
+                    value: Some(i),
+                })
+                .chain(back_choices)
+                .collect(),
+            header,
+            prompt,
+        })?;
+        match chosen.value {
+            Some(_) => Ok(chosen.key),
+            None => Err(GoBack.into()),
+        }
+    })?;
+    let index = keys
+        .iter()
+        .position(|x| *x == key)
+        .ok_or_eyre(format!("{key:?} is not one of the choices for {label:?}"))?;
+    Ok(choices.swap_remove(index))
+}
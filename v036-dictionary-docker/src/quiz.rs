@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::DictionaryEntry;
+use crate::embeddings::get_definition_hash;
+use crate::lookup_history::get_data_dir;
+use crate::scheduler::Clock;
+use crate::scheduler::ReviewProgress;
+
+pub const QUIZ_PROGRESS_FILE_NAME: &str = "quiz.json";
+/// Cap on never-seen cards per quiz so a big dictionary doesn't arrive all at once.
+pub const NEW_CARDS_PER_QUIZ: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum QuizDirection {
+    DefinitionToWord,
+    WordToDefinition,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QuizCard {
+    pub key: String,
+    pub word: String,
+    pub definition: String,
+}
+
+impl QuizCard {
+    pub fn new(entry: &DictionaryEntry) -> Self {
+        Self {
+            key: get_card_key(entry),
+            word: entry.word.clone(),
+            definition: entry.definition.clone(),
+        }
+    }
+}
+
+/// Progress follows the definition text, so an edited definition starts over.
+pub fn get_card_key(entry: &DictionaryEntry) -> String {
+    format!("{}#{}", entry.word, get_definition_hash(&entry.definition))
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct QuizProgress {
+    pub cards: HashMap<String, ReviewProgress>,
+}
+
+impl QuizProgress {
+    pub async fn read() -> eyre::Result<Self> {
+        let path = get_data_dir()?.join(QUIZ_PROGRESS_FILE_NAME);
+        if !tokio::fs::try_exists(&path).await? {
+            return Ok(Self::default());
+        }
+        let contents = tokio::fs::read_to_string(&path)
+            .await
+            .context(format!("Reading {}", path.display()))?;
+        serde_json::from_str(&contents).context(format!("Parsing {}", path.display()))
+    }
+
+    pub async fn write(&self) -> eyre::Result<()> {
+        let data_dir = get_data_dir()?;
+        tokio::fs::create_dir_all(&data_dir).await?;
+        let path = data_dir.join(QUIZ_PROGRESS_FILE_NAME);
+        tokio::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .await
+            .context(format!("Writing {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Cards due for review, most overdue first, followed by up to `new_card_limit` unseen cards.
+    pub fn get_due_cards(
+        &self,
+        dictionary: &Dictionary,
+        clock: &dyn Clock,
+        new_card_limit: usize,
+    ) -> Vec<QuizCard> {
+        let mut due: Vec<(&ReviewProgress, QuizCard)> = Vec::new();
+        let mut new = Vec::new();
+        for entry in &dictionary.entries {
+            let card = QuizCard::new(entry);
+            match self.cards.get(&card.key) {
+                Some(progress) if progress.is_due(clock) => due.push((progress, card)),
+                Some(_) => {}
+                None if new.len() < new_card_limit => new.push(card),
+                None => {}
+            }
+        }
+        due.sort_by_key(|(progress, _)| progress.due_at);
+        due.into_iter().map(|(_, card)| card).chain(new).collect()
+    }
+
+    pub fn get_next_due_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.cards.values().map(|x| x.due_at).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono::TimeZone;
+    use chrono::Utc;
+
+    use super::*;
+    use crate::scheduler::FakeClock;
+    use crate::scheduler::ReviewGrade;
+
+    fn entry(word: &str, definition: &str) -> DictionaryEntry {
+        DictionaryEntry {
+            word: word.to_string(),
+            definition: definition.to_string(),
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn due_cards_come_most_overdue_first_then_a_few_new_ones() {
+        let clock = FakeClock::new(Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap());
+        let dictionary = Dictionary::new(vec![
+            entry("a", "first"),
+            entry("b", "second"),
+            entry("c", "third"),
+            entry("d", "fourth"),
+            entry("e", "fifth"),
+        ]);
+        let key = |i: usize| get_card_key(&dictionary.entries[i]);
+        let mut progress = QuizProgress::default();
+        // b fell due a day before a, c is not due yet, d and e are new
+        let reviewed = ReviewProgress::new(&clock).review(ReviewGrade::Good, &clock);
+        progress.cards.insert(key(1), reviewed.clone());
+        clock.advance(Duration::days(1));
+        progress.cards.insert(
+            key(0),
+            ReviewProgress::new(&clock).review(ReviewGrade::Wrong, &clock),
+        );
+        progress.cards.insert(
+            key(2),
+            ReviewProgress::new(&clock)
+                .review(ReviewGrade::Good, &clock)
+                .review(ReviewGrade::Good, &clock),
+        );
+        clock.advance(Duration::days(1));
+
+        let due = progress.get_due_cards(&dictionary, &clock, 1);
+        let words = due
+            .iter()
+            .map(|card| card.word.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(words, vec!["b", "a", "d"]);
+        assert_eq!(
+            progress.get_next_due_at(),
+            Some(reviewed.due_at),
+            "the earliest due date wins"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use nanuak_core::prompt::answer;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::get_dictionary_path;
+use crate::normalize::MatchOptions;
+use crate::normalize::normalize_for_matching;
+use crate::prompt::pick_answer;
+use crate::quiz::NEW_CARDS_PER_QUIZ;
+use crate::quiz::QuizCard;
+use crate::quiz::QuizDirection;
+use crate::quiz::QuizProgress;
+use crate::scheduler::ReviewGrade;
+use crate::scheduler::ReviewProgress;
+use crate::scheduler::get_clock;
+use crate::state::State;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum QuizState {
+    #[default]
+    #[state(describe = "Choose quiz direction")]
+    ChooseDirection,
+    #[state(describe = "Load cards due for review")]
+    LoadDueCards { direction: QuizDirection },
+    #[state(describe = "Ask the next card", side_effects)]
+    AskCard {
+        direction: QuizDirection,
+        /// Taken from the dictionary when the cards are loaded, so typed answers match the same way.
+        match_options: MatchOptions,
+        cards: Vec<QuizCard>,
+        reviewed: usize,
+        passed: usize,
+    },
+    #[state(describe = "Show quiz results")]
+    ShowResults { reviewed: usize, passed: usize },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl QuizState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::ChooseDirection => {
+                let chosen = pick_answer(FzfArgs {
+                    choices: vec![
+                        Choice {
+                            key: "Show the definition, I'll type the word".to_string(),
+                            value: QuizDirection::DefinitionToWord,
+                        },
+                        Choice {
+                            key: "Show the word, I'll recall the definition".to_string(),
+                            value: QuizDirection::WordToDefinition,
+                        },
+                    ],
+                    header: Some("How should we quiz you?".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::LoadDueCards {
+                    direction: chosen.value,
+                })
+            }
+            Self::LoadDueCards { direction } => {
+                let dictionary = Dictionary::load(get_dictionary_path().await?).await?;
+                let progress = QuizProgress::read().await?;
+                let cards = progress.get_due_cards(&dictionary, &*get_clock(), NEW_CARDS_PER_QUIZ);
+                if cards.is_empty() {
+                    match progress.get_next_due_at() {
+                        Some(due_at) => println!("Nothing due, next review at {}", due_at),
+                        None => println!("Nothing to review, the dictionary is empty"),
+                    }
+                    return Ok(Self::Done);
+                }
+                info!("{} cards to review", cards.len());
+                Ok(Self::AskCard {
+                    direction,
+                    match_options: dictionary.match_options,
+                    cards,
+                    reviewed: 0,
+                    passed: 0,
+                })
+            }
+            Self::AskCard {
+                direction,
+                match_options,
+                mut cards,
+                reviewed,
+                passed,
+            } => {
+                let card = cards.remove(0);
+                let grade = ask_card(direction, &match_options, &card)?;
+
+                let clock = get_clock();
+                let mut progress = QuizProgress::read().await?;
+                let review = progress
+                    .cards
+                    .get(&card.key)
+                    .cloned()
+                    .unwrap_or_else(|| ReviewProgress::new(&*clock))
+                    .review(grade, &*clock);
+                info!("Next review of {} at {}", card.word, review.due_at);
+                progress.cards.insert(card.key.clone(), review);
+                progress.write().await?;
+
+                let reviewed = reviewed + 1;
+                let passed = passed + grade.is_pass() as usize;
+                if cards.is_empty() {
+                    return Ok(Self::ShowResults { reviewed, passed });
+                }
+                Ok(Self::AskCard {
+                    direction,
+                    match_options,
+                    cards,
+                    reviewed,
+                    passed,
+                })
+            }
+            Self::ShowResults { reviewed, passed } => {
+                println!("Passed {} of {} cards", passed, reviewed);
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
+
+/// Show one side of the card and grade the answer, letting the user rate passes themselves.
+fn ask_card(
+    direction: QuizDirection,
+    match_options: &MatchOptions,
+    card: &QuizCard,
+) -> eyre::Result<ReviewGrade> {
+    let recalled = match direction {
+        QuizDirection::DefinitionToWord => {
+            println!("{}", card.definition);
+            let typed = answer("Word", |prompt| {
+                Ok(dialoguer::Input::<String>::new()
+                    .with_prompt(prompt)
+                    .allow_empty(true)
+                    .interact_text()?)
+            })?;
+            let correct = normalize_for_matching(&typed, match_options)
+                == normalize_for_matching(&card.word, match_options);
+            if !correct {
+                println!("It was {}", console::style(&card.word).bold());
+            }
+            correct
+        }
+        QuizDirection::WordToDefinition => {
+            println!("{}", console::style(&card.word).bold());
+            answer("Press enter to reveal the definition", |prompt| {
+                Ok(dialoguer::Input::<String>::new()
+                    .with_prompt(prompt)
+                    .allow_empty(true)
+                    .interact_text()?)
+            })?;
+            println!("{}", card.definition);
+            answer_opt("Did you remember it?", |prompt| {
+                Ok(dialoguer::Confirm::new()
+                    .with_prompt(prompt)
+                    .interact_opt()?)
+            })?
+        }
+    };
+    if !recalled {
+        return Ok(ReviewGrade::Wrong);
+    }
+    let grades = [ReviewGrade::Hard, ReviewGrade::Good, ReviewGrade::Easy];
+    let chosen = answer_opt("How easy was that?", |prompt| {
+        Ok(dialoguer::Select::new()
+            .with_prompt(prompt)
+            .items(&["Hard", "Good", "Easy"])
+            .default(1)
+            .interact_opt()?)
+    })?;
+    Ok(grades[chosen])
+}
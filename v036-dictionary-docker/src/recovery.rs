@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use chrono::DateTime;
+use chrono::Utc;
+use eyre::Context;
+use nanuak_core::prompt::answer;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::sync::Notify;
+use tracing::info;
+use tracing::warn;
+
+use crate::lookup_history::get_data_dir;
+use crate::state::State;
+
+pub const RECOVERY_FILE_NAME: &str = "recovery.json";
+
+/// Exit code used when the session stopped on Ctrl-C after writing a checkpoint.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static CANCEL_CURRENT_STEP: Notify = Notify::const_new();
+
+/// The state a session was interrupted in, so the next launch can pick it back up.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecoveryCheckpoint {
+    pub interrupted_at: DateTime<Utc>,
+    pub state_type: String,
+    pub description: String,
+    pub state: serde_json::Value,
+}
+
+/// The session stopped on Ctrl-C and its state was written to `checkpoint_path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interrupted {
+    pub checkpoint_path: PathBuf,
+}
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Interrupted, the current state was saved to {}",
+            self.checkpoint_path.display()
+        )
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Listen for Ctrl-C in the background.
+///
+/// The first one lets the current step finish before the driver stops,
+/// the second cancels it unless it has side effects.
+pub fn listen_for_interrupts() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                warn!("Cancelling the current step unless it has side effects");
+                cancel_current_step();
+            } else {
+                warn!("Stopping after the current step, press Ctrl-C again to cancel it");
+            }
+        }
+    });
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Cancel the step being driven, as a second Ctrl-C does.
+pub fn cancel_current_step() {
+    CANCEL_CURRENT_STEP.notify_waiters();
+}
+
+/// Wait until the user asks to cancel the current step.
+pub async fn step_cancelled() {
+    CANCEL_CURRENT_STEP.notified().await
+}
+
+/// Whether `error` came from the user interrupting, either by signal or by Ctrl-C inside a prompt.
+pub fn is_interrupt(error: &eyre::Report) -> bool {
+    is_interrupted()
+        || error.chain().any(|cause| {
+            cause.is::<Interrupted>()
+                || cause
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::Interrupted)
+        })
+}
+
+pub fn get_recovery_file_path() -> eyre::Result<PathBuf> {
+    Ok(get_data_dir()?.join(RECOVERY_FILE_NAME))
+}
+
+pub async fn write_checkpoint(checkpoint: &RecoveryCheckpoint) -> eyre::Result<PathBuf> {
+    let path = get_recovery_file_path()?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&path, serde_json::to_string_pretty(checkpoint)?)
+        .await
+        .context(format!("Writing checkpoint to {}", path.display()))?;
+    Ok(path)
+}
+
+pub async fn read_checkpoint() -> eyre::Result<Option<RecoveryCheckpoint>> {
+    let path = get_recovery_file_path()?;
+    if !tokio::fs::try_exists(&path).await? {
+        return Ok(None);
+    }
+    let content = tokio::fs::read_to_string(&path).await?;
+    let checkpoint =
+        serde_json::from_str(&content).context(format!("Parsing checkpoint {}", path.display()))?;
+    Ok(Some(checkpoint))
+}
+
+pub async fn remove_checkpoint() -> eyre::Result<()> {
+    let path = get_recovery_file_path()?;
+    if tokio::fs::try_exists(&path).await? {
+        tokio::fs::remove_file(&path).await?;
+    }
+    Ok(())
+}
+
+/// Offer to carry on from the checkpoint left by an interrupted session, removing it either way.
+pub async fn take_checkpoint_to_resume<S: State + DeserializeOwned>() -> eyre::Result<Option<S>> {
+    let Some(checkpoint) = read_checkpoint().await? else {
+        return Ok(None);
+    };
+    let Some(state) = checkpoint.get_state::<S>()? else {
+        warn!(
+            "Ignoring a checkpoint for {} while starting {}",
+            checkpoint.state_type,
+            std::any::type_name::<S>()
+        );
+        return Ok(None);
+    };
+    let resume = answer(
+        &format!(
+            "Resume from {:?}, interrupted at {}?",
+            checkpoint.description,
+            checkpoint.interrupted_at.format("%Y-%m-%d %H:%M")
+        ),
+        |prompt| Ok(dialoguer::Confirm::new().with_prompt(prompt).interact()?),
+    )?;
+    remove_checkpoint().await?;
+    if !resume {
+        info!("Starting over instead of resuming");
+        return Ok(None);
+    }
+    info!("Resuming from {}", checkpoint.description);
+    Ok(Some(state))
+}
+
+impl RecoveryCheckpoint {
+    pub fn new<S: State>(state: &S) -> eyre::Result<Self> {
+        Ok(Self {
+            interrupted_at: Utc::now(),
+            state_type: std::any::type_name::<S>().to_string(),
+            description: state.describe(),
+            state: serde_json::to_value(state)?,
+        })
+    }
+
+    /// The checkpointed state, if it was written for a state of type `S`.
+    pub fn get_state<S: State + DeserializeOwned>(&self) -> eyre::Result<Option<S>> {
+        if self.state_type != std::any::type_name::<S>() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(self.state.clone())?))
+    }
+}
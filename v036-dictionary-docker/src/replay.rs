@@ -0,0 +1,304 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use eyre::Context;
+use eyre::bail;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::prompt::PromptAnswer;
+use nanuak_core::prompt::read_answers;
+use nanuak_core::prompt::record_answers_to;
+use nanuak_core::prompt::replay_answers;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::driver::DriverOptions;
+use crate::driver::TransitionRecord;
+use crate::driver::drive_with_options;
+use crate::lookup_history::get_data_dir;
+use crate::lookup_history::redirect_data_dir;
+use crate::scheduler::FakeClock;
+use crate::scheduler::with_clock;
+use crate::state::DictionaryApplicationState;
+
+/// Every answer given during the recorded session, one `PromptAnswer` per line.
+pub const ANSWERS_FILE_NAME: &str = "answers.jsonl";
+
+/// The transition log of the recorded session.
+pub const TRANSITIONS_FILE_NAME: &str = "transitions.jsonl";
+
+/// Where and when the session was recorded.
+pub const RECORDING_INFO_FILE_NAME: &str = "recording.json";
+
+/// The data dir as recording started, so a replay sees the same history and quiz progress.
+pub const DATA_DIR_NAME: &str = "data";
+
+/// The root dir name every state looks for, so the workspace copy must keep it.
+const WORKSPACE_DIR_NAME: &str = "Nanuak-Dictionary";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecordingInfo {
+    pub recorded_at: DateTime<Utc>,
+    pub workspace_dir: Option<PathBuf>,
+}
+
+/// A replay in progress: the recording being replayed and the workspace copy it runs in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub recording_dir: PathBuf,
+    pub recording_info: RecordingInfo,
+    pub workspace_copy_dir: PathBuf,
+    /// Where `get_data_dir` points for the rest of the replay.
+    pub data_dir: PathBuf,
+    pub transition_log: PathBuf,
+}
+
+/// Start recording answers into `recording_dir`, returning the transition log to record into.
+pub async fn start_recording(recording_dir: &Path) -> eyre::Result<PathBuf> {
+    let answers_path = recording_dir.join(ANSWERS_FILE_NAME);
+    if tokio::fs::try_exists(&answers_path).await? {
+        bail!(
+            "{} already holds a recording, choose another dir",
+            recording_dir.display()
+        );
+    }
+    tokio::fs::create_dir_all(recording_dir)
+        .await
+        .context(format!(
+            "Creating recording dir {}",
+            recording_dir.display()
+        ))?;
+    let recording_info = RecordingInfo {
+        recorded_at: Utc::now(),
+        workspace_dir: get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir()
+            .await
+            .ok(),
+    };
+    tokio::fs::write(
+        recording_dir.join(RECORDING_INFO_FILE_NAME),
+        serde_json::to_string_pretty(&recording_info)?,
+    )
+    .await?;
+    let data_dir = get_data_dir()?;
+    if tokio::fs::try_exists(&data_dir).await? {
+        copy_workspace(data_dir, recording_dir.join(DATA_DIR_NAME)).await?;
+    }
+    record_answers_to(answers_path);
+    info!("Recording this session into {}", recording_dir.display());
+    Ok(recording_dir.join(TRANSITIONS_FILE_NAME))
+}
+
+/// Copy the recorded workspace somewhere fresh, move into it and queue the recorded answers.
+///
+/// The data dir is redirected to a copy of the one recorded, so the replay reads what the
+/// session read and writes nothing the user keeps.
+///
+/// `workspace_dir` overrides the workspace the session was recorded in.
+pub async fn start_replay(
+    recording_dir: &Path,
+    workspace_dir: Option<PathBuf>,
+) -> eyre::Result<Replay> {
+    let recording_info_path = recording_dir.join(RECORDING_INFO_FILE_NAME);
+    let recording_info: RecordingInfo = serde_json::from_str(
+        &tokio::fs::read_to_string(&recording_info_path)
+            .await
+            .context(format!("Reading {}", recording_info_path.display()))?,
+    )?;
+    let Some(workspace_dir) = workspace_dir.or_else(|| recording_info.workspace_dir.clone()) else {
+        bail!("The recording has no workspace dir, pass one with --workspace");
+    };
+    let answers = read_answers(recording_dir.join(ANSWERS_FILE_NAME)).await?;
+
+    // Kept after the replay so its transition log and workspace can be looked at
+    let replay_dir = tempfile::Builder::new()
+        .prefix("nanuak-replay-")
+        .tempdir()?
+        .keep();
+    let workspace_copy_dir = replay_dir.join(WORKSPACE_DIR_NAME);
+    info!(
+        "Copying {} to {}",
+        workspace_dir.display(),
+        workspace_copy_dir.display()
+    );
+    copy_workspace(workspace_dir.clone(), workspace_copy_dir.clone()).await?;
+    std::env::set_current_dir(&workspace_copy_dir)
+        .context(format!("Moving into {}", workspace_copy_dir.display()))?;
+
+    let data_dir = replay_dir.join(DATA_DIR_NAME);
+    let recorded_data_dir = recording_dir.join(DATA_DIR_NAME);
+    if tokio::fs::try_exists(&recorded_data_dir).await? {
+        copy_workspace(recorded_data_dir, data_dir.clone()).await?;
+    } else {
+        tokio::fs::create_dir_all(&data_dir).await?;
+    }
+    info!("Keeping replayed data in {}", data_dir.display());
+    redirect_data_dir(&data_dir);
+
+    // Picked versions are recorded by path, so they have to point into the copy too
+    let answers = match &recording_info.workspace_dir {
+        Some(recorded_workspace_dir) => answers
+            .into_iter()
+            .map(|answer| PromptAnswer {
+                answer: relocate(&answer.answer, recorded_workspace_dir, &workspace_copy_dir),
+                ..answer
+            })
+            .collect(),
+        None => answers,
+    };
+    info!("Replaying {} answers", answers.len());
+    replay_answers(answers);
+    Ok(Replay {
+        recording_dir: recording_dir.to_path_buf(),
+        recording_info,
+        workspace_copy_dir,
+        data_dir,
+        transition_log: replay_dir.join(TRANSITIONS_FILE_NAME),
+    })
+}
+
+/// Drive the app from the start through the replayed answers, logging its transitions, with
+/// the clock stopped at when the session was recorded.
+pub async fn drive_replay(replay: &Replay) -> eyre::Result<DictionaryApplicationState> {
+    let clock = Arc::new(FakeClock::new(replay.recording_info.recorded_at));
+    let options = DriverOptions {
+        transition_log: Some(replay.transition_log.clone()),
+    };
+    with_clock(
+        clock,
+        drive_with_options(DictionaryApplicationState::default(), options),
+    )
+    .await
+}
+
+/// Copy a workspace leaving out build output and git history.
+async fn copy_workspace(from: PathBuf, to: PathBuf) -> eyre::Result<()> {
+    tokio::task::spawn_blocking(move || copy_workspace_dir(&from, &to)).await?
+}
+
+fn copy_workspace_dir(from: &Path, to: &Path) -> eyre::Result<()> {
+    std::fs::create_dir_all(to).context(format!("Creating {}", to.display()))?;
+    for entry in std::fs::read_dir(from).context(format!("Reading {}", from.display()))? {
+        let entry = entry?;
+        if entry.file_name() == "target" || entry.file_name() == ".git" {
+            continue;
+        }
+        let destination = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_workspace_dir(&entry.path(), &destination)?;
+        } else {
+            std::fs::copy(entry.path(), &destination)
+                .context(format!("Copying {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn read_transitions(path: impl AsRef<Path>) -> eyre::Result<Vec<TransitionRecord>> {
+    let path = path.as_ref();
+    if !tokio::fs::try_exists(path).await? {
+        return Ok(Vec::new());
+    }
+    let content = tokio::fs::read_to_string(path).await?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).context(format!(
+                "Parsing line {} of {}",
+                i + 1,
+                path.display()
+            ))
+        })
+        .collect()
+}
+
+/// Swap `from` for `to` wherever it appears in `value`.
+fn relocate(value: &serde_json::Value, from: &Path, to: &Path) -> serde_json::Value {
+    let text = value
+        .to_string()
+        .replace(&from.display().to_string(), &to.display().to_string());
+    serde_json::from_str(&text).unwrap_or_else(|_| value.clone())
+}
+
+/// Where a replay first went a different way than the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayDivergence {
+    pub index: usize,
+    pub recorded: Option<TransitionRecord>,
+    pub replayed: Option<TransitionRecord>,
+}
+
+/// Compare transitions by their states and whether they failed, ignoring timings.
+///
+/// Recorded states mention the original workspace dir, so it is swapped for the copy first.
+pub fn find_divergence(
+    recorded: &[TransitionRecord],
+    replayed: &[TransitionRecord],
+    recorded_workspace_dir: Option<&Path>,
+    workspace_copy_dir: &Path,
+) -> Option<ReplayDivergence> {
+    let relocate = |value: &serde_json::Value| match recorded_workspace_dir {
+        Some(dir) => relocate(value, dir, workspace_copy_dir),
+        None => value.clone(),
+    };
+    let is_same = |recorded: &TransitionRecord, replayed: &TransitionRecord| {
+        recorded.state_type == replayed.state_type
+            && relocate(&recorded.before) == replayed.before
+            && recorded.after.as_ref().map(relocate) == replayed.after
+            && recorded.error.is_some() == replayed.error.is_some()
+    };
+    let len = recorded.len().max(replayed.len());
+    (0..len)
+        .find(|&i| match (recorded.get(i), replayed.get(i)) {
+            (Some(a), Some(b)) => !is_same(a, b),
+            _ => true,
+        })
+        .map(|index| ReplayDivergence {
+            index,
+            recorded: recorded.get(index).cloned(),
+            replayed: replayed.get(index).cloned(),
+        })
+}
+
+/// Check the replayed transitions against the recorded ones, failing at the first difference.
+pub async fn check_replay(replay: &Replay) -> eyre::Result<()> {
+    let recorded = read_transitions(replay.recording_dir.join(TRANSITIONS_FILE_NAME)).await?;
+    let replayed = read_transitions(&replay.transition_log).await?;
+    match find_divergence(
+        &recorded,
+        &replayed,
+        replay.recording_info.workspace_dir.as_deref(),
+        &replay.workspace_copy_dir,
+    ) {
+        None => {
+            info!(
+                "Replay matched all {} recorded transitions in {}",
+                recorded.len(),
+                replay.workspace_copy_dir.display()
+            );
+            Ok(())
+        }
+        Some(divergence) => bail!(
+            "Replay diverged at transition {}\nrecorded: {}\nreplayed: {}\nThe replayed transitions are in {}",
+            divergence.index,
+            describe_transition(divergence.recorded.as_ref()),
+            describe_transition(divergence.replayed.as_ref()),
+            replay.transition_log.display()
+        ),
+    }
+}
+
+fn describe_transition(record: Option<&TransitionRecord>) -> String {
+    match record {
+        None => "nothing".to_string(),
+        Some(record) => match (&record.after, &record.error) {
+            (_, Some(error)) => format!("{} failed: {}", record.before, error),
+            (Some(after), None) => format!("{} -> {}", record.before, after),
+            (None, None) => format!("{} -> ?", record.before),
+        },
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use cloud_terrastodon_core_user_input::prelude::Choice;
+use cloud_terrastodon_core_user_input::prelude::FzfArgs;
+use eyre::OptionExt;
+use eyre::bail;
+use itertools::Itertools;
+use nanuak_core::cargo_toml;
+use nanuak_core::get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir;
+use nanuak_core::prompt::answer_opt;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::archive::ArchiveManifest;
+use crate::archive::extract_archive;
+use crate::archive::get_archives;
+use crate::archive::read_archive_manifest;
+use crate::prompt::pick_answer;
+use crate::state::State;
+use crate::workspace::read_workspace_cargo_toml;
+use crate::workspace::write_workspace_cargo_toml;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum RestoreVersionsState {
+    #[default]
+    #[state(describe = "Determine workspace dir")]
+    DetermineWorkspaceDir,
+    #[state(describe = "Choose archive to restore")]
+    ChooseArchive { workspace_dir: PathBuf },
+    #[state(describe = "Extract archive", side_effects)]
+    ExtractArchive {
+        workspace_dir: PathBuf,
+        archive_path: PathBuf,
+    },
+    #[state(describe = "Update workspace Cargo.toml", side_effects, idempotent)]
+    UpdateWorkspaceCargoToml {
+        workspace_dir: PathBuf,
+        archive_path: PathBuf,
+        manifest: ArchiveManifest,
+    },
+    #[state(describe = "Remove archive", side_effects)]
+    RemoveArchive { archive_path: PathBuf },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl RestoreVersionsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::DetermineWorkspaceDir => {
+                info!("Find the root dir containing the versions");
+                let workspace_dir =
+                    get_nanuak_dictionary_root_dir_using_cwd_if_matches_or_parent_dir().await?;
+                Ok(Self::ChooseArchive { workspace_dir })
+            }
+            Self::ChooseArchive { workspace_dir } => {
+                let archives = get_archives(&workspace_dir).await?;
+                if archives.is_empty() {
+                    bail!("No archives found in {}", workspace_dir.display());
+                }
+                let chosen = pick_answer(FzfArgs {
+                    choices: archives
+                        .into_iter()
+                        .map(|archive| Choice {
+                            key: archive.display().to_string(),
+                            value: archive,
+                        })
+                        .collect_vec(),
+                    header: Some("Choose an archive to restore".to_string()),
+                    prompt: None,
+                })?;
+                Ok(Self::ExtractArchive {
+                    workspace_dir,
+                    archive_path: chosen.value,
+                })
+            }
+            Self::ExtractArchive {
+                workspace_dir,
+                archive_path,
+            } => {
+                let manifest = read_archive_manifest(archive_path.clone()).await?;
+                for version in &manifest.versions {
+                    let version_dir = workspace_dir.join(&version.name);
+                    if tokio::fs::try_exists(&version_dir).await? {
+                        bail!(
+                            "Refusing to restore over existing directory {}",
+                            version_dir.display()
+                        );
+                    }
+                }
+                info!(
+                    "Extracting {} into {}",
+                    archive_path.display(),
+                    workspace_dir.display()
+                );
+                extract_archive(archive_path.clone(), workspace_dir.clone()).await?;
+                Ok(Self::UpdateWorkspaceCargoToml {
+                    workspace_dir,
+                    archive_path,
+                    manifest,
+                })
+            }
+            Self::UpdateWorkspaceCargoToml {
+                workspace_dir,
+                archive_path,
+                manifest,
+            } => {
+                info!("Add the restored versions back to the workspace Cargo.toml");
+                let mut workspace_cargo_toml = read_workspace_cargo_toml(&workspace_dir).await?;
+                let workspace = workspace_cargo_toml
+                    .workspace
+                    .as_mut()
+                    .ok_or_eyre("No workspace")?;
+                for version in &manifest.versions {
+                    if version.workspace_member && !workspace.members.contains(&version.name) {
+                        workspace.members.push(version.name.clone());
+                    }
+                    if let Some(path) = &version.workspace_dependency_path {
+                        if workspace.dependencies.0.contains_key(&version.name) {
+                            warn!(
+                                "Workspace dependency {} already exists, leaving it as is",
+                                version.name
+                            );
+                        } else {
+                            workspace.dependencies.0.insert(
+                                version.name.clone(),
+                                cargo_toml::Dependency::Path {
+                                    path: path.clone(),
+                                    features: None,
+                                },
+                            );
+                        }
+                    }
+                }
+                write_workspace_cargo_toml(&workspace_dir, &workspace_cargo_toml).await?;
+                Ok(Self::RemoveArchive { archive_path })
+            }
+            Self::RemoveArchive { archive_path } => {
+                let remove = answer_opt(
+                    &format!("Delete {} now that it is restored?", archive_path.display()),
+                    |prompt| {
+                        Ok(dialoguer::Confirm::new()
+                            .with_prompt(prompt)
+                            .interact_opt()?)
+                    },
+                )?;
+                if remove {
+                    tokio::fs::remove_file(&archive_path).await?;
+                    info!("Removed {}", archive_path.display());
+                }
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
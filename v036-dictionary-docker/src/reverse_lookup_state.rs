@@ -0,0 +1,67 @@
+use eyre::OptionExt;
+use nanuak_core::prompt::answer;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::get_dictionary_path;
+use crate::embeddings::Embedder;
+use crate::embeddings::EmbeddingCache;
+use crate::embeddings::OllamaEmbedder;
+use crate::embeddings::embed_dictionary;
+use crate::embeddings::nearest_words;
+use crate::state::State;
+
+pub const REVERSE_LOOKUP_LIMIT: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum ReverseLookupState {
+    #[default]
+    #[state(describe = "Prompt me for a description of a word")]
+    PromptingForDescription,
+    #[state(describe = "Find the nearest words")]
+    FindingNearestWords { description: String },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl ReverseLookupState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::PromptingForDescription => {
+                let description = answer("Describe the word you are looking for", |prompt| {
+                    Ok(dialoguer::Input::<String>::new()
+                        .with_prompt(prompt)
+                        .interact_text()?)
+                })?;
+                Ok(Self::FindingNearestWords { description })
+            }
+            Self::FindingNearestWords { description } => {
+                let dictionary_path = get_dictionary_path().await?;
+                let dictionary = Dictionary::load(&dictionary_path).await?;
+                let embedder = OllamaEmbedder::from_config();
+
+                let cache_path = EmbeddingCache::get_path(&dictionary_path);
+                let mut cache =
+                    EmbeddingCache::load_or_default(&cache_path, &embedder.model()).await?;
+                let vectors = embed_dictionary(&dictionary, &embedder, &mut cache).await?;
+                cache.save(&cache_path).await?;
+                info!("Embeddings cached in {}", cache_path.display());
+
+                let query_vector = embedder
+                    .embed(&[description])
+                    .await?
+                    .into_iter()
+                    .next()
+                    .ok_or_eyre("No embedding returned for the description")?;
+                for (word, similarity) in
+                    nearest_words(&dictionary, &vectors, &query_vector, REVERSE_LOOKUP_LIMIT)
+                {
+                    println!("{:>6.3}  {}", similarity, word);
+                }
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! SM-2 spaced repetition scheduling, kept free of IO so it can run against a fake clock.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub const INITIAL_EASE_FACTOR: f64 = 2.5;
+pub const MINIMUM_EASE_FACTOR: f64 = 1.3;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+tokio::task_local! {
+    /// Set by `with_clock` for everything it runs.
+    static CLOCK: Arc<dyn Clock>;
+}
+
+/// The clock given to the `with_clock` call this runs under, or the system clock outside one.
+pub fn get_clock() -> Arc<dyn Clock> {
+    CLOCK
+        .try_with(Arc::clone)
+        .unwrap_or_else(|_| Arc::new(SystemClock))
+}
+
+/// Run `future` with `get_clock` returning `clock`, like a replay pinned to when it was recorded.
+pub async fn with_clock<F: Future>(clock: Arc<dyn Clock>, future: F) -> F::Output {
+    CLOCK.scope(clock, future).await
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FakeClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// How well an answer was recalled, on SM-2's 0 to 5 scale.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReviewGrade {
+    Blackout = 0,
+    Wrong = 1,
+    WrongButFamiliar = 2,
+    Hard = 3,
+    Good = 4,
+    Easy = 5,
+}
+
+impl ReviewGrade {
+    pub fn quality(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn is_pass(&self) -> bool {
+        self.quality() >= 3
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReviewProgress {
+    pub ease_factor: f64,
+    pub interval_days: i64,
+    pub repetitions: u32,
+    pub due_at: DateTime<Utc>,
+    pub last_reviewed_at: Option<DateTime<Utc>>,
+}
+
+impl ReviewProgress {
+    /// A card that has never been reviewed and is due straight away.
+    pub fn new(clock: &dyn Clock) -> Self {
+        Self {
+            ease_factor: INITIAL_EASE_FACTOR,
+            interval_days: 0,
+            repetitions: 0,
+            due_at: clock.now(),
+            last_reviewed_at: None,
+        }
+    }
+
+    pub fn is_due(&self, clock: &dyn Clock) -> bool {
+        self.due_at <= clock.now()
+    }
+
+    /// The progress after answering with `grade` now.
+    pub fn review(&self, grade: ReviewGrade, clock: &dyn Clock) -> Self {
+        let now = clock.now();
+        let quality = grade.quality() as f64;
+        let ease_factor = (self.ease_factor
+            + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)))
+            .max(MINIMUM_EASE_FACTOR);
+        let (repetitions, interval_days) = if grade.is_pass() {
+            let interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f64 * self.ease_factor).round() as i64,
+            };
+            (self.repetitions + 1, interval_days)
+        } else {
+            (0, 1)
+        };
+        Self {
+            ease_factor,
+            interval_days,
+            repetitions,
+            due_at: now + Duration::days(interval_days),
+            last_reviewed_at: Some(now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn each_grade_moves_the_ease_factor_by_the_sm2_amount() {
+        let clock = FakeClock::new(start());
+        let expected = [
+            (ReviewGrade::Blackout, 1.7),
+            (ReviewGrade::Wrong, 1.96),
+            (ReviewGrade::WrongButFamiliar, 2.18),
+            (ReviewGrade::Hard, 2.36),
+            (ReviewGrade::Good, 2.5),
+            (ReviewGrade::Easy, 2.6),
+        ];
+        for (grade, ease_factor) in expected {
+            let progress = ReviewProgress::new(&clock).review(grade, &clock);
+            assert!(
+                (progress.ease_factor - ease_factor).abs() < 1e-9,
+                "{grade:?} gave {} instead of {ease_factor}",
+                progress.ease_factor
+            );
+        }
+    }
+
+    #[test]
+    fn each_grade_sets_the_interval_and_due_date() {
+        let clock = FakeClock::new(start());
+        let seen_twice = ReviewProgress::new(&clock)
+            .review(ReviewGrade::Good, &clock)
+            .review(ReviewGrade::Good, &clock);
+        for grade in [
+            ReviewGrade::Blackout,
+            ReviewGrade::Wrong,
+            ReviewGrade::WrongButFamiliar,
+        ] {
+            let progress = seen_twice.review(grade, &clock);
+            assert_eq!(progress.repetitions, 0, "{grade:?}");
+            assert_eq!(progress.interval_days, 1, "{grade:?}");
+            assert_eq!(progress.due_at, start() + Duration::days(1), "{grade:?}");
+        }
+        for grade in [ReviewGrade::Hard, ReviewGrade::Good, ReviewGrade::Easy] {
+            let progress = seen_twice.review(grade, &clock);
+            assert_eq!(progress.repetitions, 3, "{grade:?}");
+            assert_eq!(progress.interval_days, 15, "{grade:?}");
+            assert_eq!(progress.due_at, start() + Duration::days(15), "{grade:?}");
+            assert_eq!(progress.last_reviewed_at, Some(start()), "{grade:?}");
+        }
+    }
+
+    #[test]
+    fn passing_reviews_grow_the_interval() {
+        let clock = FakeClock::new(start());
+        let mut progress = ReviewProgress::new(&clock);
+        let mut intervals = Vec::new();
+        for _ in 0..4 {
+            progress = progress.review(ReviewGrade::Good, &clock);
+            intervals.push(progress.interval_days);
+            clock.advance(Duration::days(progress.interval_days));
+            assert!(progress.is_due(&clock));
+        }
+        assert_eq!(intervals, vec![1, 6, 15, 38]);
+    }
+
+    #[test]
+    fn ease_factor_stops_at_the_minimum() {
+        let clock = FakeClock::new(start());
+        let mut progress = ReviewProgress::new(&clock);
+        for _ in 0..5 {
+            progress = progress.review(ReviewGrade::Blackout, &clock);
+        }
+        assert_eq!(progress.ease_factor, MINIMUM_EASE_FACTOR);
+    }
+
+    #[test]
+    fn a_card_is_due_once_its_interval_has_passed() {
+        let clock = FakeClock::new(start());
+        let progress = ReviewProgress::new(&clock);
+        assert!(progress.is_due(&clock));
+        let progress = progress.review(ReviewGrade::Good, &clock);
+        assert!(!progress.is_due(&clock));
+        clock.advance(Duration::hours(23));
+        assert!(!progress.is_due(&clock));
+        clock.advance(Duration::hours(1));
+        assert!(progress.is_due(&clock));
+    }
+}
@@ -0,0 +1,64 @@
+use nanuak_core::prompt::answer;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::get_dictionary_path;
+use crate::search_index::DefinitionIndex;
+use crate::search_index::highlight_terms;
+use crate::state::State;
+
+pub const SEARCH_RESULT_LIMIT: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+pub enum SearchDefinitionsState {
+    #[default]
+    #[state(describe = "Prompt me for words from a definition")]
+    PromptingForQuery,
+    #[state(describe = "Search definitions")]
+    SearchingDefinitions { query: String },
+    #[state(describe = "Done", terminal)]
+    Done,
+}
+impl SearchDefinitionsState {
+    async fn transition(self) -> eyre::Result<Self> {
+        match self {
+            Self::PromptingForQuery => {
+                let query = answer("Enter words you remember from the definition", |prompt| {
+                    Ok(dialoguer::Input::<String>::new()
+                        .with_prompt(prompt)
+                        .interact_text()?)
+                })?;
+                Ok(Self::SearchingDefinitions { query })
+            }
+            Self::SearchingDefinitions { query } => {
+                let dictionary = Dictionary::load(get_dictionary_path().await?).await?;
+                info!("Index {} definitions", dictionary.entries.len());
+                let index = DefinitionIndex::build(&dictionary);
+                let hits = index.search(&query, SEARCH_RESULT_LIMIT);
+                if hits.is_empty() {
+                    println!("No definitions match {:?}", query);
+                }
+                for hit in hits {
+                    let entry = &dictionary.entries[hit.entry_index];
+                    let definition = highlight_terms(
+                        &entry.definition,
+                        &hit.matched_terms,
+                        &dictionary.match_options,
+                        |x| console::style(x).bold().yellow().to_string(),
+                    );
+                    println!(
+                        "{:>6.2}  {}: {} ({})",
+                        hit.score,
+                        console::style(&entry.word).bold(),
+                        definition,
+                        entry.source
+                    );
+                }
+                Ok(Self::Done)
+            }
+            Self::Done => Ok(Self::Done),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::dictionary::Dictionary;
+use crate::normalize::MatchOptions;
+use crate::normalize::is_word_char;
+use crate::normalize::normalize_for_matching;
+
+/// BM25 term frequency saturation.
+pub const BM25_K1: f64 = 1.2;
+/// BM25 document length normalisation.
+pub const BM25_B: f64 = 0.75;
+
+/// Normalised runs of word characters, so "Bear's" yields "bear" and "s".
+pub fn tokenize(text: &str, options: &MatchOptions) -> Vec<String> {
+    text.split(|c: char| !is_word_char(c))
+        .filter(|token| !token.is_empty())
+        .map(|token| normalize_for_matching(token, options))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// Index into `Dictionary::entries`.
+    pub entry_index: usize,
+    pub score: f64,
+    pub matched_terms: Vec<String>,
+}
+
+/// An inverted index over the tokenised definitions of a dictionary, ranked with BM25.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DefinitionIndex {
+    /// Term to (entry index, term frequency) postings.
+    pub postings: HashMap<String, Vec<(usize, usize)>>,
+    pub document_lengths: Vec<usize>,
+    pub average_document_length: f64,
+    pub match_options: MatchOptions,
+}
+
+impl DefinitionIndex {
+    pub fn build(dictionary: &Dictionary) -> Self {
+        let mut postings: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        let mut document_lengths = Vec::with_capacity(dictionary.entries.len());
+        for (entry_index, entry) in dictionary.entries.iter().enumerate() {
+            let tokens = tokenize(&entry.definition, &dictionary.match_options);
+            document_lengths.push(tokens.len());
+            let mut term_frequencies: HashMap<String, usize> = HashMap::new();
+            for token in tokens {
+                *term_frequencies.entry(token).or_default() += 1;
+            }
+            for (term, frequency) in term_frequencies {
+                postings
+                    .entry(term)
+                    .or_default()
+                    .push((entry_index, frequency));
+            }
+        }
+        let average_document_length = if document_lengths.is_empty() {
+            0.0
+        } else {
+            document_lengths.iter().sum::<usize>() as f64 / document_lengths.len() as f64
+        };
+        Self {
+            postings,
+            document_lengths,
+            average_document_length,
+            match_options: dictionary.match_options,
+        }
+    }
+
+    fn inverse_document_frequency(&self, term: &str) -> f64 {
+        let document_count = self.document_lengths.len() as f64;
+        let containing = self.postings.get(term).map(|x| x.len()).unwrap_or(0) as f64;
+        ((document_count - containing + 0.5) / (containing + 0.5) + 1.0).ln()
+    }
+
+    /// The best `limit` entries for `query`, highest score first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let terms: Vec<String> = tokenize(query, &self.match_options)
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mut hits: HashMap<usize, SearchHit> = HashMap::new();
+        for term in terms {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+            let idf = self.inverse_document_frequency(&term);
+            for &(entry_index, frequency) in postings {
+                let frequency = frequency as f64;
+                let length_ratio =
+                    self.document_lengths[entry_index] as f64 / self.average_document_length;
+                let score = idf * (frequency * (BM25_K1 + 1.0))
+                    / (frequency + BM25_K1 * (1.0 - BM25_B + BM25_B * length_ratio));
+                let hit = hits.entry(entry_index).or_insert_with(|| SearchHit {
+                    entry_index,
+                    score: 0.0,
+                    matched_terms: Vec::new(),
+                });
+                hit.score += score;
+                hit.matched_terms.push(term.clone());
+            }
+        }
+        let mut hits: Vec<SearchHit> = hits.into_values().collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(a.entry_index.cmp(&b.entry_index))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Wrap every token of `text` that tokenises to one of `terms` with `highlight`, keeping the
+/// rest of the text as written.
+pub fn highlight_terms(
+    text: &str,
+    terms: &[String],
+    options: &MatchOptions,
+    highlight: impl Fn(&str) -> String,
+) -> String {
+    let mut rtn = String::with_capacity(text.len());
+    let mut token_start: Option<usize> = None;
+    let push_token = |rtn: &mut String, token: &str| {
+        if terms.contains(&normalize_for_matching(token, options)) {
+            rtn.push_str(&highlight(token));
+        } else {
+            rtn.push_str(token);
+        }
+    };
+    for (i, c) in text.char_indices() {
+        if is_word_char(c) {
+            token_start.get_or_insert(i);
+        } else {
+            if let Some(start) = token_start.take() {
+                push_token(&mut rtn, &text[start..i]);
+            }
+            rtn.push(c);
+        }
+    }
+    if let Some(start) = token_start {
+        push_token(&mut rtn, &text[start..]);
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::DictionaryEntry;
+
+    fn get_index(definitions: &[&str]) -> DefinitionIndex {
+        let entries = definitions
+            .iter()
+            .enumerate()
+            .map(|(i, definition)| DictionaryEntry {
+                word: format!("word{i}"),
+                definition: definition.to_string(),
+                source: "test".to_string(),
+            })
+            .collect();
+        DefinitionIndex::build(&Dictionary::new(entries))
+    }
+
+    fn get_ranking(index: &DefinitionIndex, query: &str) -> Vec<usize> {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|x| x.entry_index)
+            .collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn scores_a_single_term_with_bm25() {
+        let index = get_index(&["large bear", "small bear cub", "tiny fish"]);
+        assert_eq!(index.document_lengths, vec![2, 3, 2]);
+        assert_close(index.average_document_length, 7.0 / 3.0);
+
+        let hits = index.search("bear", 10);
+        // Two of three definitions contain "bear"
+        let idf = ((3.0 - 2.0 + 0.5) / (2.0 + 0.5) + 1.0_f64).ln();
+        let score = |length: f64| {
+            idf * (BM25_K1 + 1.0) / (1.0 + BM25_K1 * (1.0 - BM25_B + BM25_B * length / (7.0 / 3.0)))
+        };
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].entry_index, 0);
+        assert_close(hits[0].score, score(2.0));
+        assert_eq!(hits[1].entry_index, 1);
+        assert_close(hits[1].score, score(3.0));
+        assert_eq!(hits[0].matched_terms, vec!["bear".to_string()]);
+    }
+
+    #[test]
+    fn rarer_terms_and_repeats_rank_higher() {
+        let index = get_index(&[
+            "a bear in a den",
+            "a bear and a bear cub",
+            "a wolf in a den",
+            "a bear",
+        ]);
+        // "wolf" is in one definition and "bear" in three, so wolf outweighs bear
+        assert_eq!(get_ranking(&index, "bear wolf"), vec![2, 3, 1, 0]);
+        // Repeating "bear" beats the extra length of the second definition
+        assert_eq!(get_ranking(&index, "bear")[..2], [3, 1]);
+        assert!(index.search("moose", 10).is_empty());
+        assert_eq!(index.search("bear", 1).len(), 1);
+    }
+
+    #[test]
+    fn ties_keep_dictionary_order() {
+        let index = get_index(&["red fox", "grey fox", "red deer"]);
+        let hits = index.search("fox", 10);
+        assert_close(hits[0].score, hits[1].score);
+        assert_eq!(get_ranking(&index, "fox"), vec![0, 1]);
+        assert_eq!(get_ranking(&index, "FOX fox"), vec![0, 1]);
+    }
+
+    #[test]
+    fn highlights_whole_tokens_only() {
+        let terms = vec!["bear".to_string(), "bears".to_string()];
+        let highlighted = highlight_terms(
+            "Bears bear; bearing bear's-weight. Bear",
+            &terms,
+            &MatchOptions::default(),
+            |x| format!("[{x}]"),
+        );
+        assert_eq!(
+            highlighted,
+            "[Bears] [bear]; bearing [bear]'s-weight. [Bear]"
+        );
+    }
+
+    #[test]
+    fn highlights_accented_terms_as_written() {
+        let options = MatchOptions {
+            ignore_diacritics: true,
+            ..Default::default()
+        };
+        let terms = tokenize("cafe", &options);
+        assert_eq!(
+            highlight_terms("Café au lait, cafe\u{301}", &terms, &options, |x| format!(
+                "<{x}>"
+            )),
+            "<Café> au lait, <cafe\u{301}>"
+        );
+    }
+}
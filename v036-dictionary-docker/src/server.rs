@@ -0,0 +1,175 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use eyre::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use tower_http::trace::TraceLayer;
+use tracing::info;
+
+use crate::dictionary::Dictionary;
+use crate::dictionary::DictionaryEntry;
+
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Health {
+    pub status: String,
+    pub entries: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchParams {
+    pub q: String,
+}
+
+pub fn router(dictionary: Arc<Dictionary>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/define/{word}", get(define))
+        .route("/search", get(search))
+        .route("/sources", get(sources))
+        .layer(TraceLayer::new_for_http())
+        .with_state(dictionary)
+}
+
+async fn health(State(dictionary): State<Arc<Dictionary>>) -> Json<Health> {
+    Json(Health {
+        status: "ok".to_string(),
+        entries: dictionary.entries.len(),
+    })
+}
+
+async fn define(
+    State(dictionary): State<Arc<Dictionary>>,
+    Path(word): Path<String>,
+) -> Result<Json<Vec<DictionaryEntry>>, StatusCode> {
+    let entries = dictionary.define(&word);
+    if entries.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(entries.into_iter().cloned().collect()))
+}
+
+async fn search(
+    State(dictionary): State<Arc<Dictionary>>,
+    Query(params): Query<SearchParams>,
+) -> Json<Vec<DictionaryEntry>> {
+    Json(dictionary.search(&params.q).into_iter().cloned().collect())
+}
+
+async fn sources(State(dictionary): State<Arc<Dictionary>>) -> Json<Vec<String>> {
+    Json(dictionary.sources())
+}
+
+pub async fn serve(addr: SocketAddr, dictionary: Dictionary) -> eyre::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context(format!("Binding {addr}"))?;
+    info!(
+        "Serving the dictionary on http://{}",
+        listener.local_addr()?
+    );
+    axum::serve(listener, router(Arc::new(dictionary)))
+        .await
+        .context("Serving the dictionary")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde::de::DeserializeOwned;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn get_router() -> Router {
+        let entry = |word: &str, definition: &str, source: &str| DictionaryEntry {
+            word: word.to_string(),
+            definition: definition.to_string(),
+            source: source.to_string(),
+        };
+        router(Arc::new(Dictionary::new(vec![
+            entry("kettle", "A pot for boiling water", "wiktionary"),
+            entry("teapot", "A pot for brewing tea", "glossary"),
+            entry("spoon", "A utensil for stirring", "wiktionary"),
+        ])))
+    }
+
+    async fn get_json<T: DeserializeOwned>(uri: &str) -> eyre::Result<(StatusCode, Option<T>)> {
+        let response = get_router()
+            .oneshot(Request::get(uri).body(Body::empty())?)
+            .await?;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let json = match status.is_success() {
+            true => Some(serde_json::from_slice(&body)?),
+            false => None,
+        };
+        Ok((status, json))
+    }
+
+    fn get_words(entries: &[DictionaryEntry]) -> Vec<&str> {
+        entries.iter().map(|x| x.word.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn health_counts_the_entries() -> eyre::Result<()> {
+        let (status, health) = get_json::<Health>("/health").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            health,
+            Some(Health {
+                status: "ok".to_string(),
+                entries: 3,
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn define_finds_known_words_and_404s_otherwise() -> eyre::Result<()> {
+        let (status, entries) = get_json::<Vec<DictionaryEntry>>("/define/KETTLE").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(get_words(&entries.unwrap_or_default()), vec!["kettle"]);
+
+        let (status, entries) = get_json::<Vec<DictionaryEntry>>("/define/saucepan").await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(entries.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_matches_words_and_definitions() -> eyre::Result<()> {
+        let (status, entries) = get_json::<Vec<DictionaryEntry>>("/search?q=pot").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            get_words(&entries.unwrap_or_default()),
+            vec!["kettle", "teapot"]
+        );
+
+        let (status, _) = get_json::<Vec<DictionaryEntry>>("/search").await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "q is required");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sources_are_listed_once_each() -> eyre::Result<()> {
+        let (status, sources) = get_json::<Vec<String>>("/sources").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            sources,
+            Some(vec!["glossary".to_string(), "wiktionary".to_string()])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How an action chosen from the menu ended.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ActionOutcome {
+    /// It reached its terminal state, possibly after retrying a failed step.
+    Completed,
+    /// It was left for the menu after a step failed, carrying the last error.
+    Failed { error: String },
+}
+
+/// What happened to one action chosen from the menu during this session.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionRecord {
+    pub action: String,
+    pub outcome: ActionOutcome,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    history: Vec<SessionRecord>,
+    /// The action being run, when it started and the last error from its steps.
+    current: Option<(String, Instant, Option<String>)>,
+}
+
+tokio::task_local! {
+    /// Set by `with_session` for the driver, outside of it nothing is recorded.
+    static SESSION: RefCell<Session>;
+}
+
+/// Run `fut` with an empty session history that the actions it drives are recorded in.
+pub async fn with_session<F: Future>(fut: F) -> F::Output {
+    SESSION.scope(RefCell::default(), fut).await
+}
+
+fn with_current_session<T>(f: impl FnOnce(&mut Session) -> T) -> Option<T> {
+    SESSION
+        .try_with(|session| f(&mut session.borrow_mut()))
+        .ok()
+}
+
+/// Note that `action` was chosen, ending any earlier action that never completed.
+pub fn start_action(action: &str) {
+    end_unfinished_action();
+    with_current_session(|session| {
+        session.current = Some((action.to_string(), Instant::now(), None));
+    });
+}
+
+/// Note that a step of the current action failed, which is its outcome unless it completes later.
+pub fn fail_action(error: String) {
+    with_current_session(|session| {
+        if let Some((_, _, last_error)) = &mut session.current {
+            *last_error = Some(error);
+        }
+    });
+}
+
+/// Record the current action as completed.
+pub fn complete_action() {
+    with_current_session(|session| {
+        if let Some((action, started, _)) = session.current.take() {
+            session.history.push(SessionRecord {
+                action,
+                outcome: ActionOutcome::Completed,
+                elapsed: started.elapsed(),
+            });
+        }
+    });
+}
+
+/// Record an action the user skipped out of after it failed.
+pub fn end_unfinished_action() {
+    with_current_session(|session| {
+        if let Some((action, started, error)) = session.current.take() {
+            session.history.push(SessionRecord {
+                action,
+                outcome: ActionOutcome::Failed {
+                    error: error.unwrap_or_else(|| "Left unfinished".to_string()),
+                },
+                elapsed: started.elapsed(),
+            });
+        }
+    });
+}
+
+pub fn get_history() -> Vec<SessionRecord> {
+    with_current_session(|session| session.history.clone()).unwrap_or_default()
+}
+
+pub fn render_history(history: &[SessionRecord]) -> String {
+    if history.is_empty() {
+        return "Nothing done yet this session".to_string();
+    }
+    let mut rtn = String::new();
+    for (i, record) in history.iter().enumerate() {
+        let outcome = match &record.outcome {
+            ActionOutcome::Completed => "done".to_string(),
+            ActionOutcome::Failed { error } => format!("failed: {error}"),
+        };
+        rtn.push_str(&format!(
+            "{:>3}. {} ({}, {:.1}s)\n",
+            i + 1,
+            record.action,
+            outcome,
+            record.elapsed.as_secs_f64()
+        ));
+    }
+    rtn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_how_each_action_ended() {
+        let history = with_session(async {
+            start_action("DefineWord");
+            fail_action("No network".to_string());
+            complete_action();
+            start_action("Quiz");
+            fail_action("No cards".to_string());
+            end_unfinished_action();
+            get_history()
+        })
+        .await;
+        let outcomes = history
+            .into_iter()
+            .map(|record| (record.action, record.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("DefineWord".to_string(), ActionOutcome::Completed),
+                (
+                    "Quiz".to_string(),
+                    ActionOutcome::Failed {
+                        error: "No cards".to_string()
+                    }
+                ),
+            ]
+        );
+        assert!(get_history().is_empty(), "nothing outlives the session");
+    }
+}
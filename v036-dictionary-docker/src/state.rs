@@ -1,89 +1,210 @@
 use cloud_terrastodon_core_user_input::prelude::Choice;
 use cloud_terrastodon_core_user_input::prelude::FzfArgs;
-use cloud_terrastodon_core_user_input::prelude::pick;
+use nanuak_core::prompt::is_go_back;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
 
+use crate::archive_versions_state::ArchiveVersionsState;
+use crate::browse_lookups_state::BrowseLookupsState;
+use crate::capture_diagnostics_state::CaptureDiagnosticsState;
 use crate::create_new_version_state::CreateNewVersionState;
 use crate::define_word_state::DefineWordState;
+use crate::dockerize_version_state::DockerizeVersionState;
+use crate::driver::drive;
+use crate::driver::step;
+use crate::generate_next_version_state::GenerateNextVersionState;
+use crate::promote_shared_items_state::PromoteSharedItemsState;
+use crate::prompt::pick_answer;
+use crate::quiz_state::QuizState;
+use crate::recovery::is_interrupt;
+use crate::restore_versions_state::RestoreVersionsState;
+use crate::reverse_lookup_state::ReverseLookupState;
+use crate::search_definitions_state::SearchDefinitionsState;
+use crate::session::complete_action;
+use crate::session::end_unfinished_action;
+use crate::session::fail_action;
+use crate::session::get_history;
+use crate::session::render_history;
+use crate::session::start_action;
+use crate::state_graph::StateGraph;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub use nanuak_state_derive::State;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, State)]
+#[state(delegate = step_session_action)]
 pub enum DictionaryApplicationState {
     #[default]
+    #[state(describe = "Start the application", next = Self::choose_action())]
     JustLaunchedNoArgs,
+    #[state(skip = Self::JustLaunchedNoArgs)]
     DefineWord(DefineWordState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    BrowseHistory(BrowseLookupsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    BrowseFavourites(BrowseLookupsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    SearchDefinitions(SearchDefinitionsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    ReverseLookup(ReverseLookupState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    Quiz(QuizState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
     CreateNewVersion(CreateNewVersionState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    ArchiveVersions(ArchiveVersionsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    RestoreVersions(RestoreVersionsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    PromoteSharedItems(PromoteSharedItemsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    CaptureDiagnostics(CaptureDiagnosticsState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    GenerateNextVersion(GenerateNextVersionState),
+    #[state(skip = Self::JustLaunchedNoArgs)]
+    DockerizeVersion(DockerizeVersionState),
+    #[state(describe = "Show session history", next = Self::show_session_history())]
+    ShowSessionHistory,
+    #[state(describe = "Quit", next = Self::quit())]
+    Quit,
+    #[state(describe = "Done", terminal)]
     Done,
 }
-const INITIAL_ACTIONS: [DictionaryApplicationState; 2] = [
+const INITIAL_ACTIONS: [DictionaryApplicationState; 15] = [
     DictionaryApplicationState::DefineWord(DefineWordState::PromptingForWordToDefine),
+    DictionaryApplicationState::BrowseHistory(BrowseLookupsState::PickFromHistory),
+    DictionaryApplicationState::BrowseFavourites(BrowseLookupsState::PickFromFavourites),
+    DictionaryApplicationState::SearchDefinitions(SearchDefinitionsState::PromptingForQuery),
+    DictionaryApplicationState::ReverseLookup(ReverseLookupState::PromptingForDescription),
+    DictionaryApplicationState::Quiz(QuizState::ChooseDirection),
     DictionaryApplicationState::CreateNewVersion(
         CreateNewVersionState::DetermineWorkspaceCargoTomlPath,
     ),
+    DictionaryApplicationState::ArchiveVersions(ArchiveVersionsState::DetermineWorkspaceDir),
+    DictionaryApplicationState::RestoreVersions(RestoreVersionsState::DetermineWorkspaceDir),
+    DictionaryApplicationState::PromoteSharedItems(PromoteSharedItemsState::DetermineWorkspaceDir),
+    DictionaryApplicationState::CaptureDiagnostics(CaptureDiagnosticsState::DetermineWorkspaceDir),
+    DictionaryApplicationState::GenerateNextVersion(
+        GenerateNextVersionState::DetermineWorkspaceDir,
+    ),
+    DictionaryApplicationState::DockerizeVersion(DockerizeVersionState::DetermineWorkspaceDir),
+    DictionaryApplicationState::ShowSessionHistory,
+    DictionaryApplicationState::Quit,
 ];
 
+/// Usually implemented with `#[derive(State)]`, see `nanuak_state_derive`.
 #[async_trait::async_trait]
-pub trait State: Sized {
+pub trait State: Sized + Serialize + Send + Clone {
+    /// The name of the variant this state is in.
+    fn label(&self) -> &'static str;
     fn describe(&self) -> String;
     async fn next(self) -> eyre::Result<Self>
     where
         Self: Sized;
     fn is_terminal(&self) -> bool;
 
+    /// Whether leaving this state changes something outside of it, like files on disk.
+    ///
+    /// The driver will not go back past such a state since its changes can't be undone.
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+
+    /// Whether running this state again after it failed is safe, which the driver needs before
+    /// offering to retry it.
+    fn is_idempotent(&self) -> bool {
+        !self.has_side_effects()
+    }
+
+    /// The state to carry on from if the user skips this one after it failed, if it can be skipped.
+    fn skip(&self) -> Option<Self> {
+        None
+    }
+
     async fn next_until_terminal(self) -> eyre::Result<Self> {
-        let mut state = self;
-        loop {
-            info!("Applying state: {}", state.describe());
-            state = state.next().await?;
-            info!("Next state: {}", state.describe());
-            if state.is_terminal() {
-                break;
-            }
-        }
-        Ok(state)
+        drive(self).await
     }
 }
 
-#[async_trait::async_trait]
-impl State for DictionaryApplicationState {
-    fn describe(&self) -> String {
-        match self {
-            Self::JustLaunchedNoArgs => "Start the application".to_string(),
-            Self::DefineWord(state) => format!("DefineWord - {}", state.describe()),
-            Self::CreateNewVersion(state) => format!("CreateNewVersion - {}", state.describe()),
-            Self::Done => "Done".to_string(),
+impl DictionaryApplicationState {
+    async fn choose_action() -> eyre::Result<Self> {
+        end_unfinished_action();
+        let chosen = pick_answer(FzfArgs {
+            choices: INITIAL_ACTIONS
+                .iter()
+                .map(|action| Choice {
+                    key: action.describe().to_string(),
+                    value: action,
+                })
+                .collect(),
+            header: Some("Choose an action".to_string()),
+            prompt: None,
+        })?;
+        if !matches!(chosen.value, Self::ShowSessionHistory | Self::Quit) {
+            start_action(chosen.value.label());
         }
+        Ok(chosen.value.clone())
     }
-    async fn next(self) -> eyre::Result<Self> {
-        match self {
-            Self::JustLaunchedNoArgs => {
-                let chosen = pick(FzfArgs {
-                    choices: INITIAL_ACTIONS
-                        .iter()
-                        .map(|action| Choice {
-                            key: action.describe().to_string(),
-                            value: action,
-                        })
-                        .collect(),
-                    header: Some("Choose an action".to_string()),
-                    prompt: None,
-                })?;
-                Ok(chosen.value.clone())
-            }
-            Self::DefineWord(state) => {
-                _ = state.next_until_terminal().await?;
-                Ok(Self::Done)
-            }
-            Self::CreateNewVersion(state) => {
-                _ = state.next_until_terminal().await?;
-                Ok(Self::Done)
-            }
-            Self::Done => Ok(Self::Done),
+
+    async fn show_session_history() -> eyre::Result<Self> {
+        println!("{}", render_history(&get_history()));
+        Ok(Self::JustLaunchedNoArgs)
+    }
+
+    async fn quit() -> eyre::Result<Self> {
+        info!("Leaving after {} actions", get_history().len());
+        Ok(Self::Done)
+    }
+}
+
+impl StateGraph for DictionaryApplicationState {
+    fn get_examples() -> Vec<Self> {
+        let mut examples = vec![Self::JustLaunchedNoArgs];
+        examples.extend(INITIAL_ACTIONS);
+        examples.push(Self::Done);
+        examples
+    }
+
+    fn get_transitions() -> Vec<(&'static str, &'static str)> {
+        let actions = INITIAL_ACTIONS
+            .iter()
+            .filter(|action| !matches!(action, Self::ShowSessionHistory | Self::Quit))
+            .map(|action| action.label());
+        let mut transitions = vec![
+            ("JustLaunchedNoArgs", "ShowSessionHistory"),
+            ("JustLaunchedNoArgs", "Quit"),
+            ("ShowSessionHistory", "JustLaunchedNoArgs"),
+            ("Quit", "Done"),
+        ];
+        for action in actions {
+            // Actions step one inner state at a time before going back to the menu.
+            transitions.push(("JustLaunchedNoArgs", action));
+            transitions.push((action, action));
+            transitions.push((action, "JustLaunchedNoArgs"));
         }
+        transitions
     }
+}
 
-    fn is_terminal(&self) -> bool {
-        matches!(self, Self::Done)
+/// Advance a chosen action by one step, going back to the menu once it is done.
+///
+/// A failed step is noted against the action in the session history and left to the driver,
+/// which can retry it or skip the action to go back to the menu.
+async fn step_session_action<S: State>(
+    action: &str,
+    state: S,
+    wrap: fn(S) -> DictionaryApplicationState,
+) -> eyre::Result<DictionaryApplicationState> {
+    match step(state).await {
+        Ok(next) if next.is_terminal() => {
+            complete_action();
+            Ok(DictionaryApplicationState::JustLaunchedNoArgs)
+        }
+        Ok(next) => Ok(wrap(next)),
+        Err(e) if is_go_back(&e) || is_interrupt(&e) => Err(e),
+        Err(e) => {
+            fail_action(format!("{e:#}"));
+            Err(e.wrap_err(format!("{action} failed")))
+        }
     }
 }
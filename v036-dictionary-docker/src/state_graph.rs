@@ -0,0 +1,431 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use eyre::OptionExt;
+use eyre::bail;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::create_new_version_state::CreateNewVersionState;
+use crate::define_word_state::DefineWordState;
+use crate::driver::TransitionRecord;
+use crate::replay::read_transitions;
+use crate::state::DictionaryApplicationState;
+use crate::state::State;
+
+/// The shape of a state machine, declared next to its `State` impl.
+pub trait StateGraph: State + Default {
+    /// One value of every variant, used to name and describe the nodes.
+    fn get_examples() -> Vec<Self>;
+
+    /// Every transition `next` can make, as pairs of variant names.
+    fn get_transitions() -> Vec<(&'static str, &'static str)>;
+}
+
+/// The state machines that can be drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StateMachine {
+    App,
+    CreateNewVersion,
+    DefineWord,
+}
+
+impl StateMachine {
+    pub fn get_diagram(self) -> eyre::Result<StateDiagram> {
+        match self {
+            Self::App => StateDiagram::of::<DictionaryApplicationState>(),
+            Self::CreateNewVersion => StateDiagram::of::<CreateNewVersionState>(),
+            Self::DefineWord => StateDiagram::of::<DefineWordState>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiagramFormat {
+    Mermaid,
+    Graphviz,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StateNode {
+    pub name: String,
+    pub description: String,
+    pub is_terminal: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StateDiagram {
+    pub state_type: String,
+    pub initial: String,
+    pub nodes: Vec<StateNode>,
+    pub transitions: Vec<(String, String)>,
+}
+
+/// The variant a serialized state is in, which is the whole value for unit variants
+/// and the only key otherwise.
+pub fn get_variant_name(value: &serde_json::Value) -> Option<&str> {
+    match value {
+        serde_json::Value::String(name) => Some(name),
+        serde_json::Value::Object(map) if map.len() == 1 => map.keys().next().map(|x| x.as_str()),
+        _ => None,
+    }
+}
+
+pub fn get_state_variant_name<S: State>(state: &S) -> eyre::Result<String> {
+    let value = serde_json::to_value(state)?;
+    Ok(get_variant_name(&value)
+        .ok_or_eyre(format!("{} is not an enum variant", value))?
+        .to_string())
+}
+
+impl StateDiagram {
+    pub fn of<S: StateGraph>() -> eyre::Result<Self> {
+        let nodes = S::get_examples()
+            .iter()
+            .map(|example| {
+                Ok(StateNode {
+                    name: get_state_variant_name(example)?,
+                    description: example.describe(),
+                    is_terminal: example.is_terminal(),
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        Ok(Self {
+            state_type: std::any::type_name::<S>().to_string(),
+            initial: get_state_variant_name(&S::default())?,
+            nodes,
+            transitions: S::get_transitions()
+                .into_iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Fail on transitions between unknown variants, variants that can't be reached
+    /// from the initial one and non-terminal variants with no way out.
+    pub fn check(&self) -> eyre::Result<()> {
+        let names = self
+            .nodes
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect::<BTreeSet<_>>();
+        let mut problems = Vec::new();
+        for (from, to) in &self.transitions {
+            for name in [from, to] {
+                if !names.contains(name.as_str()) {
+                    problems.push(format!("{from} -> {to} mentions unknown variant {name}"));
+                }
+            }
+        }
+        let mut reachable = BTreeSet::from([self.initial.as_str()]);
+        loop {
+            let before = reachable.len();
+            for (from, to) in &self.transitions {
+                if reachable.contains(from.as_str()) {
+                    reachable.insert(to.as_str());
+                }
+            }
+            if reachable.len() == before {
+                break;
+            }
+        }
+        for node in &self.nodes {
+            if !reachable.contains(node.name.as_str()) {
+                problems.push(format!(
+                    "{} can't be reached from {}",
+                    node.name, self.initial
+                ));
+            }
+            if !node.is_terminal && !self.transitions.iter().any(|(from, _)| *from == node.name) {
+                problems.push(format!("{} has no transitions out", node.name));
+            }
+        }
+        if !problems.is_empty() {
+            bail!(
+                "The declared transitions of {} don't hold up:\n{}",
+                self.state_type,
+                problems.join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    pub fn render(&self, format: DiagramFormat) -> String {
+        match format {
+            DiagramFormat::Mermaid => self.render_mermaid(),
+            DiagramFormat::Graphviz => self.render_graphviz(),
+        }
+    }
+
+    pub fn render_mermaid(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "stateDiagram-v2").unwrap();
+        for node in &self.nodes {
+            writeln!(
+                out,
+                "    {}: {}",
+                node.name,
+                node.description.replace(':', "")
+            )
+            .unwrap();
+        }
+        writeln!(out, "    [*] --> {}", self.initial).unwrap();
+        for (from, to) in &self.transitions {
+            writeln!(out, "    {from} --> {to}").unwrap();
+        }
+        for node in self.nodes.iter().filter(|x| x.is_terminal) {
+            writeln!(out, "    {} --> [*]", node.name).unwrap();
+        }
+        out
+    }
+
+    pub fn render_graphviz(&self) -> String {
+        let mut out = String::new();
+        let title = self
+            .state_type
+            .rsplit("::")
+            .next()
+            .unwrap_or(&self.state_type);
+        writeln!(out, "digraph {title} {{").unwrap();
+        writeln!(out, "    rankdir=TB;").unwrap();
+        for node in &self.nodes {
+            let shape = if node.is_terminal {
+                "doublecircle"
+            } else {
+                "box"
+            };
+            writeln!(
+                out,
+                "    {} [shape={}, label=\"{}\\n{}\"];",
+                node.name,
+                shape,
+                node.name,
+                node.description.replace('"', "\\\"")
+            )
+            .unwrap();
+        }
+        for (from, to) in &self.transitions {
+            writeln!(out, "    {from} -> {to};").unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+/// Transitions seen in a transition log, split by whether they were declared.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransitionCoverage {
+    pub observed: BTreeSet<(String, String)>,
+    pub undeclared: BTreeSet<(String, String)>,
+    pub unobserved: BTreeSet<(String, String)>,
+}
+
+/// Compare what actually happened in a transition log with what the diagram declares.
+pub fn get_transition_coverage(
+    diagram: &StateDiagram,
+    records: &[TransitionRecord],
+) -> TransitionCoverage {
+    let declared = diagram.transitions.iter().cloned().collect::<BTreeSet<_>>();
+    let observed = records
+        .iter()
+        .filter(|record| record.state_type == diagram.state_type)
+        .filter_map(|record| {
+            let from = get_variant_name(&record.before)?;
+            let to = get_variant_name(record.after.as_ref()?)?;
+            Some((from.to_string(), to.to_string()))
+        })
+        .collect::<BTreeSet<_>>();
+    TransitionCoverage {
+        undeclared: observed.difference(&declared).cloned().collect(),
+        unobserved: declared.difference(&observed).cloned().collect(),
+        observed,
+    }
+}
+
+/// Print the diagram, or with transition logs, check them against the declared transitions.
+pub async fn run_states_command(
+    machine: StateMachine,
+    format: DiagramFormat,
+    transition_logs: &[PathBuf],
+) -> eyre::Result<()> {
+    let diagram = machine.get_diagram()?;
+    diagram.check()?;
+    if transition_logs.is_empty() {
+        print!("{}", diagram.render(format));
+        return Ok(());
+    }
+    let mut records = Vec::new();
+    for path in transition_logs {
+        records.extend(read_transitions(path).await?);
+    }
+    let coverage = get_transition_coverage(&diagram, &records);
+    info!(
+        "Saw {} of {} declared transitions",
+        coverage.observed.len() - coverage.undeclared.len(),
+        diagram.transitions.len()
+    );
+    for (from, to) in &coverage.unobserved {
+        info!("Never saw {} -> {}", from, to);
+    }
+    if !coverage.undeclared.is_empty() {
+        bail!(
+            "Saw transitions that {} does not declare:\n{}",
+            diagram.state_type,
+            coverage
+                .undeclared
+                .iter()
+                .map(|(from, to)| format!("{from} -> {to}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use nanuak_core::prompt::PromptAnswer;
+    use nanuak_core::prompt::get_remaining_answer_count;
+    use nanuak_core::prompt::replay_answers;
+    use serde::Serialize;
+
+    use super::*;
+    use crate::dictionary::DICTIONARY_FILE_NAME;
+    use crate::driver::DriverOptions;
+    use crate::driver::drive_with_options;
+    use crate::lookup_history::redirect_data_dir;
+
+    fn answer(prompt: &str, answer: impl Serialize) -> PromptAnswer {
+        PromptAnswer {
+            prompt: prompt.to_string(),
+            answer: serde_json::to_value(answer).unwrap(),
+            went_back: false,
+        }
+    }
+
+    async fn write_version(workspace_dir: &Path, name: &str, main: &str) -> eyre::Result<()> {
+        let version_dir = workspace_dir.join(name);
+        tokio::fs::create_dir_all(version_dir.join("src")).await?;
+        tokio::fs::write(
+            version_dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2024\"\n"),
+        )
+        .await?;
+        tokio::fs::write(version_dir.join("src/main.rs"), main).await?;
+        Ok(())
+    }
+
+    /// Defines a word, creates a version that checks cleanly, creates one that doesn't and rolls
+    /// it back, then shows the history and quits, logging every transition on the way.
+    #[tokio::test]
+    async fn replayed_session_makes_every_declared_transition() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let workspace_dir = tokio::fs::canonicalize(dir.path())
+            .await?
+            .join("Nanuak-Dictionary");
+        tokio::fs::create_dir_all(&workspace_dir).await?;
+        tokio::fs::write(
+            workspace_dir.join("Cargo.toml"),
+            "[workspace]\nresolver = \"2\"\nmembers = [\"v001-good\", \"v002-broken\"]\n\n[workspace.dependencies]\n",
+        )
+        .await?;
+        write_version(&workspace_dir, "v001-good", "fn main() {}\n").await?;
+        write_version(
+            &workspace_dir,
+            "v002-broken",
+            "fn main() {\n    let _: u32 = \"not a number\";\n}\n",
+        )
+        .await?;
+        tokio::fs::write(
+            workspace_dir.join(DICTIONARY_FILE_NAME),
+            r#"[{"word": "kettle", "definition": "A pot for boiling water", "source": "test"}]"#,
+        )
+        .await?;
+        redirect_data_dir(dir.path().join("data"));
+        let previous_dir = std::env::current_dir()?;
+        std::env::set_current_dir(&workspace_dir)?;
+
+        let menu = "Choose an action";
+        let copy = "Choose a version to copy";
+        replay_answers(vec![
+            answer(
+                menu,
+                DictionaryApplicationState::DefineWord(DefineWordState::default()).describe(),
+            ),
+            answer("Enter a word", "kettle"),
+            answer(
+                menu,
+                DictionaryApplicationState::CreateNewVersion(CreateNewVersionState::default())
+                    .describe(),
+            ),
+            answer(copy, workspace_dir.join("v001-good").display().to_string()),
+            answer("Enter the name for version v003", "v003-good"),
+            answer(
+                menu,
+                DictionaryApplicationState::CreateNewVersion(CreateNewVersionState::default())
+                    .describe(),
+            ),
+            answer(
+                copy,
+                workspace_dir.join("v002-broken").display().to_string(),
+            ),
+            answer("Enter the name for version v004", "v004-broken"),
+            answer("What should happen to v004-broken?", 1),
+            answer(
+                menu,
+                DictionaryApplicationState::ShowSessionHistory.describe(),
+            ),
+            answer(menu, DictionaryApplicationState::Quit.describe()),
+        ]);
+        let transition_log = dir.path().join("transitions.jsonl");
+        let result = drive_with_options(
+            DictionaryApplicationState::default(),
+            DriverOptions {
+                transition_log: Some(transition_log.clone()),
+            },
+        )
+        .await;
+        std::env::set_current_dir(previous_dir)?;
+        assert_eq!(result?, DictionaryApplicationState::Done);
+        assert_eq!(get_remaining_answer_count(), 0);
+        assert!(workspace_dir.join("v003-good").exists());
+        assert!(!workspace_dir.join("v004-broken").exists());
+
+        let records = read_transitions(&transition_log).await?;
+        for diagram in [
+            StateDiagram::of::<DefineWordState>()?,
+            StateDiagram::of::<CreateNewVersionState>()?,
+        ] {
+            let coverage = get_transition_coverage(&diagram, &records);
+            assert!(coverage.undeclared.is_empty(), "{coverage:?}");
+            assert!(coverage.unobserved.is_empty(), "{coverage:?}");
+        }
+
+        // The other actions need Docker, an LLM endpoint or embeddings, so only the menu
+        // transitions of the actions driven above are expected.
+        let driven = [
+            "JustLaunchedNoArgs",
+            "DefineWord",
+            "CreateNewVersion",
+            "ShowSessionHistory",
+            "Quit",
+            "Done",
+        ];
+        let diagram = StateDiagram::of::<DictionaryApplicationState>()?;
+        let coverage = get_transition_coverage(&diagram, &records);
+        assert!(coverage.undeclared.is_empty(), "{coverage:?}");
+        let not_driven = diagram
+            .transitions
+            .iter()
+            .filter(|(from, to)| !driven.contains(&from.as_str()) || !driven.contains(&to.as_str()))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        assert_eq!(coverage.unobserved, not_driven);
+        Ok(())
+    }
+}
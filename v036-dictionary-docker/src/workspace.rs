@@ -0,0 +1,442 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use eyre::Context;
+use eyre::OptionExt;
+use nanuak_core::cargo_toml;
+use nanuak_core::copy_dir_all;
+use nanuak_core::extract_next_version_number;
+use nanuak_core::get_versions;
+use nanuak_core::prompt_next_version_name_with_recommended_suffix;
+use nanuak_core::version_name::get_recommended_suffix;
+use nanuak_core::version_name::validate_next_version_name;
+use tracing::info;
+use tracing::warn;
+
+pub async fn read_workspace_cargo_toml(
+    workspace_dir: impl AsRef<Path>,
+) -> eyre::Result<cargo_toml::CargoToml> {
+    let workspace_cargo_toml_path = workspace_dir.as_ref().join("Cargo.toml");
+    let workspace_cargo_toml = tokio::fs::read_to_string(&workspace_cargo_toml_path)
+        .await
+        .context(format!(
+            "Reading {} as CargoToml",
+            workspace_cargo_toml_path.display()
+        ))?;
+    let workspace_cargo_toml: cargo_toml::CargoToml = toml::from_str(&workspace_cargo_toml)
+        .context(format!(
+            "Interpreting cargo toml from {}",
+            workspace_cargo_toml_path.display()
+        ))?;
+    Ok(workspace_cargo_toml)
+}
+
+pub async fn write_workspace_cargo_toml(
+    workspace_dir: impl AsRef<Path>,
+    workspace_cargo_toml: &cargo_toml::CargoToml,
+) -> eyre::Result<()> {
+    let workspace_cargo_toml_path = workspace_dir.as_ref().join("Cargo.toml");
+    tokio::fs::write(
+        &workspace_cargo_toml_path,
+        toml::to_string(workspace_cargo_toml)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// The paths under `src` that `dst` doesn't have yet, relative to both.
+///
+/// A missing dir is listed on its own rather than along with everything in it. Like
+/// [`copy_version_dir`], this leaves out `target`.
+pub async fn get_missing_paths(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+) -> eyre::Result<Vec<PathBuf>> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    let mut rtn = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(dir) = pending.pop() {
+        let mut children = tokio::fs::read_dir(src.join(&dir)).await?;
+        while let Some(entry) = children.next_entry().await? {
+            if dir.as_os_str().is_empty() && entry.file_name() == "target" {
+                continue;
+            }
+            let path = dir.join(entry.file_name());
+            if !tokio::fs::try_exists(dst.join(&path)).await? {
+                rtn.push(path);
+            } else if entry.file_type().await?.is_dir() {
+                pending.push(path);
+            }
+        }
+    }
+    rtn.sort();
+    Ok(rtn)
+}
+
+/// Remove each of `paths`, relative to `dir`, skipping any already gone.
+pub async fn remove_paths(dir: impl AsRef<Path>, paths: &[PathBuf]) -> eyre::Result<()> {
+    for path in paths {
+        let path = dir.as_ref().join(path);
+        if !tokio::fs::try_exists(&path).await? {
+            continue;
+        }
+        if tokio::fs::metadata(&path).await?.is_dir() {
+            tokio::fs::remove_dir_all(&path).await?;
+        } else {
+            tokio::fs::remove_file(&path).await?;
+        }
+        info!("Removed {}", path.display());
+    }
+    Ok(())
+}
+
+/// A workspace member whose Cargo.toml points at another version via `path = "..."`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathDependent {
+    pub dependent: String,
+    pub dependency: String,
+}
+
+/// The directory names of the crates that the crate in `crate_dir` depends on by path.
+pub async fn read_path_dependency_names(crate_dir: impl AsRef<Path>) -> eyre::Result<Vec<String>> {
+    let crate_dir = crate_dir.as_ref();
+    let cargo_toml_path = crate_dir.join("Cargo.toml");
+    let cargo_toml = tokio::fs::read_to_string(&cargo_toml_path)
+        .await
+        .context(format!("Reading {}", cargo_toml_path.display()))?;
+    // Parsed loosely since member manifests may use sections CargoToml doesn't model
+    let cargo_toml: toml::Table =
+        toml::from_str(&cargo_toml).context(format!("Parsing {}", cargo_toml_path.display()))?;
+    let mut rtn = Vec::new();
+    for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(dependencies) = cargo_toml.get(section).and_then(|x| x.as_table()) else {
+            continue;
+        };
+        for dependency in dependencies.values() {
+            let Some(path) = dependency.get("path").and_then(|x| x.as_str()) else {
+                continue;
+            };
+            let Some(dependency_name) = crate_dir
+                .join(path)
+                .components()
+                .next_back()
+                .map(|x| x.as_os_str().to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            rtn.push(dependency_name);
+        }
+    }
+    Ok(rtn)
+}
+
+/// The crates that `version_name` needs to build, itself first, following path dependencies transitively.
+pub async fn get_transitive_path_dependencies(
+    workspace_dir: impl AsRef<Path>,
+    version_name: &str,
+) -> eyre::Result<Vec<String>> {
+    let workspace_dir = workspace_dir.as_ref();
+    let mut rtn = vec![version_name.to_string()];
+    let mut i = 0;
+    while i < rtn.len() {
+        for dependency_name in read_path_dependency_names(workspace_dir.join(&rtn[i])).await? {
+            if !rtn.contains(&dependency_name) {
+                rtn.push(dependency_name);
+            }
+        }
+        i += 1;
+    }
+    Ok(rtn)
+}
+
+/// Find the workspace members, excluding `candidates` themselves, that depend on any of `candidates` by path.
+pub async fn get_path_dependents(
+    workspace_dir: impl AsRef<Path>,
+    candidates: &[String],
+) -> eyre::Result<Vec<PathDependent>> {
+    let workspace_dir = workspace_dir.as_ref();
+    let workspace_cargo_toml = read_workspace_cargo_toml(workspace_dir).await?;
+    let members = &workspace_cargo_toml
+        .workspace
+        .as_ref()
+        .ok_or_eyre("No workspace")?
+        .members;
+
+    let mut rtn = Vec::new();
+    for member in members.iter().filter(|member| !candidates.contains(member)) {
+        for dependency_name in read_path_dependency_names(workspace_dir.join(member)).await? {
+            if candidates.contains(&dependency_name) {
+                rtn.push(PathDependent {
+                    dependent: member.clone(),
+                    dependency: dependency_name,
+                });
+            }
+        }
+    }
+    Ok(rtn)
+}
+
+/// Prompt for a next version name until it is valid among the versions in `workspace_dir`.
+pub async fn prompt_valid_next_version_name(
+    workspace_dir: impl AsRef<Path>,
+    next_version_number: usize,
+    recommended_suffix: Option<&str>,
+) -> eyre::Result<String> {
+    let versions = get_versions(workspace_dir).await?;
+    let mut next_version_name =
+        prompt_next_version_name_with_recommended_suffix(next_version_number, recommended_suffix)
+            .await?;
+    while let Err(e) = validate_next_version_name(&next_version_name, &versions) {
+        warn!("Error: {:#}", e);
+        next_version_name = prompt_next_version_name_with_recommended_suffix(
+            next_version_number,
+            recommended_suffix,
+        )
+        .await?;
+    }
+    Ok(next_version_name)
+}
+
+/// Copy a version dir, leaving out its `target` dir.
+pub async fn copy_version_dir(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> eyre::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    tokio::fs::create_dir_all(dst).await?;
+    let mut entries = tokio::fs::read_dir(src).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name() == "target" {
+            continue;
+        }
+        let path = dst.join(entry.file_name());
+        if entry.file_type().await?.is_dir() {
+            copy_dir_all(entry.path(), &path).await?;
+        } else {
+            tokio::fs::copy(entry.path(), &path).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Set the package name in the Cargo.toml of the version in `version_dir`.
+pub async fn rename_version_package(
+    version_dir: impl AsRef<Path>,
+    version_name: &str,
+) -> eyre::Result<()> {
+    let cargo_toml_path = version_dir.as_ref().join("Cargo.toml");
+    let cargo_toml = tokio::fs::read_to_string(&cargo_toml_path)
+        .await
+        .context(format!("Reading {}", cargo_toml_path.display()))?;
+    // Parsed loosely so sections CargoToml doesn't model survive the rename
+    let mut cargo_toml: toml::Table =
+        toml::from_str(&cargo_toml).context(format!("Parsing {}", cargo_toml_path.display()))?;
+    cargo_toml
+        .get_mut("package")
+        .and_then(|x| x.as_table_mut())
+        .ok_or_eyre(format!(
+            "Expected \"package\" to be present in {}",
+            cargo_toml_path.display()
+        ))?
+        .insert(
+            "name".to_string(),
+            toml::Value::String(version_name.to_string()),
+        );
+    tokio::fs::write(&cargo_toml_path, toml::to_string(&cargo_toml)?).await?;
+    Ok(())
+}
+
+/// Point the crate idents in the main.rs of `version_dir` at `version_name` instead of
+/// `template_version_name`. Library-only versions have no main.rs and are left alone.
+pub async fn rename_version_crate_in_main(
+    version_dir: impl AsRef<Path>,
+    template_version_name: &str,
+    version_name: &str,
+) -> eyre::Result<()> {
+    let main_rs_path = version_dir.as_ref().join("src").join("main.rs");
+    if !tokio::fs::try_exists(&main_rs_path).await? {
+        return Ok(());
+    }
+    let find = template_version_name.replace("-", "_");
+    let replace = version_name.replace("-", "_");
+    info!(
+        "Update the main.rs file at {} to replace {} with {}",
+        main_rs_path.display(),
+        find,
+        replace
+    );
+    let main_rs = tokio::fs::read_to_string(&main_rs_path).await?;
+    let main_rs = main_rs.replace(&find, &replace);
+    tokio::fs::write(&main_rs_path, main_rs).await?;
+    Ok(())
+}
+
+/// Make the relative `path` dependencies of the crate in `crate_dir` absolute, resolving them
+/// against `original_crate_dir`, so a copy of a crate builds outside the workspace.
+pub async fn pin_path_dependencies(
+    crate_dir: impl AsRef<Path>,
+    original_crate_dir: impl AsRef<Path>,
+) -> eyre::Result<()> {
+    let cargo_toml_path = crate_dir.as_ref().join("Cargo.toml");
+    let cargo_toml = tokio::fs::read_to_string(&cargo_toml_path)
+        .await
+        .context(format!("Reading {}", cargo_toml_path.display()))?;
+    let mut cargo_toml: toml::Table =
+        toml::from_str(&cargo_toml).context(format!("Parsing {}", cargo_toml_path.display()))?;
+    let original_crate_dir = tokio::fs::canonicalize(original_crate_dir.as_ref()).await?;
+    for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(dependencies) = cargo_toml.get_mut(section).and_then(|x| x.as_table_mut()) else {
+            continue;
+        };
+        for (_, dependency) in dependencies.iter_mut() {
+            let Some(path) = dependency.get_mut("path") else {
+                continue;
+            };
+            let Some(relative) = path.as_str().filter(|x| Path::new(x).is_relative()) else {
+                continue;
+            };
+            let pinned = original_crate_dir.join(relative);
+            *path = toml::Value::String(pinned.to_string_lossy().to_string());
+        }
+    }
+    tokio::fs::write(&cargo_toml_path, toml::to_string(&cargo_toml)?).await?;
+    Ok(())
+}
+
+/// Prompt for a valid name for the next version, recommending the suffix of `template_version_name`.
+pub async fn prompt_unregistered_version_name(
+    workspace_dir: impl AsRef<Path>,
+    template_version_name: &str,
+) -> eyre::Result<String> {
+    let workspace_dir = workspace_dir.as_ref();
+    let versions = get_versions(workspace_dir).await?;
+    let next_version_number = extract_next_version_number(&versions).await?;
+    let recommended_suffix = get_recommended_suffix(template_version_name);
+
+    info!("Prompt the user for the name of the next version");
+    prompt_valid_next_version_name(
+        workspace_dir,
+        next_version_number,
+        recommended_suffix.as_deref(),
+    )
+    .await
+}
+
+/// Copy `template_version_name` to `next_version_name` with its package renamed, returning the
+/// new version dir.
+///
+/// This is [`crate::create_new_version_state::CreateNewVersionState`] without registering the
+/// copy as a workspace member, since it is expected not to build yet.
+pub async fn copy_version_as_unregistered_version(
+    workspace_dir: impl AsRef<Path>,
+    template_version_name: &str,
+    next_version_name: &str,
+) -> eyre::Result<PathBuf> {
+    let workspace_dir = workspace_dir.as_ref();
+    let template_version_dir = workspace_dir.join(template_version_name);
+    let next_version_dir = workspace_dir.join(next_version_name);
+
+    copy_version_dir(&template_version_dir, &next_version_dir)
+        .await
+        .context(format!(
+            "Copying {} to {}",
+            template_version_dir.display(),
+            next_version_dir.display()
+        ))?;
+
+    info!("Rename the package to {}", next_version_name);
+    rename_version_package(&next_version_dir, next_version_name).await?;
+    rename_version_crate_in_main(&next_version_dir, template_version_name, next_version_name)
+        .await?;
+
+    Ok(next_version_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn removing_missing_paths_keeps_what_was_already_there() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template_dir = dir.path().join("v001-template");
+        let next_dir = dir.path().join("v002-next");
+        tokio::fs::create_dir_all(template_dir.join("src/nested")).await?;
+        tokio::fs::write(template_dir.join("Cargo.toml"), "template").await?;
+        tokio::fs::write(template_dir.join("src/main.rs"), "template").await?;
+        tokio::fs::write(template_dir.join("src/nested/mod.rs"), "template").await?;
+        tokio::fs::create_dir_all(next_dir.join("src")).await?;
+        tokio::fs::write(next_dir.join("src/main.rs"), "mine").await?;
+        tokio::fs::write(next_dir.join("notes.md"), "mine").await?;
+
+        let missing = get_missing_paths(&template_dir, &next_dir).await?;
+        assert_eq!(
+            missing,
+            vec![PathBuf::from("Cargo.toml"), PathBuf::from("src/nested")]
+        );
+
+        copy_dir_all(&template_dir, &next_dir).await?;
+        remove_paths(&next_dir, &missing).await?;
+        assert!(!tokio::fs::try_exists(next_dir.join("Cargo.toml")).await?);
+        assert!(!tokio::fs::try_exists(next_dir.join("src/nested")).await?);
+        assert!(tokio::fs::try_exists(next_dir.join("src/main.rs")).await?);
+        assert_eq!(
+            tokio::fs::read_to_string(next_dir.join("notes.md")).await?,
+            "mine"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn copied_version_builds_outside_the_workspace() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let version_dir = dir.path().join("v002-next");
+        tokio::fs::create_dir_all(version_dir.join("target/debug")).await?;
+        tokio::fs::create_dir_all(dir.path().join("nanuak-core")).await?;
+        tokio::fs::write(
+            version_dir.join("Cargo.toml"),
+            "[package]\nname = \"v002-next\"\n\n[dependencies.nanuak-core]\npath = \"../nanuak-core\"\n",
+        )
+        .await?;
+        tokio::fs::write(version_dir.join("target/debug/v002-next"), "binary").await?;
+
+        let scratch_dir = dir.path().join("scratch");
+        copy_version_dir(&version_dir, &scratch_dir).await?;
+        pin_path_dependencies(&scratch_dir, &version_dir).await?;
+
+        assert!(!tokio::fs::try_exists(scratch_dir.join("target")).await?);
+        let cargo_toml: toml::Table =
+            toml::from_str(&tokio::fs::read_to_string(scratch_dir.join("Cargo.toml")).await?)?;
+        let path = cargo_toml["dependencies"]["nanuak-core"]["path"]
+            .as_str()
+            .ok_or_eyre("Expected a path")?;
+        assert_eq!(
+            tokio::fs::canonicalize(path).await?,
+            tokio::fs::canonicalize(dir.path().join("nanuak-core")).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renaming_a_copy_keeps_its_dev_dependencies() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template_dir = dir.path().join("v001-template");
+        let next_dir = dir.path().join("v002-next");
+        tokio::fs::create_dir_all(template_dir.join("src")).await?;
+        tokio::fs::write(
+            template_dir.join("Cargo.toml"),
+            "[package]\nname = \"v001-template\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\neyre = \"0.6.12\"\n\n[dev-dependencies]\ntempfile = \"3.19.1\"\n\n[[bin]]\nname = \"v001-template\"\npath = \"src/main.rs\"\n",
+        )
+        .await?;
+
+        copy_version_dir(&template_dir, &next_dir).await?;
+        rename_version_package(&next_dir, "v002-next").await?;
+
+        let cargo_toml: toml::Table =
+            toml::from_str(&tokio::fs::read_to_string(next_dir.join("Cargo.toml")).await?)?;
+        assert_eq!(cargo_toml["package"]["name"].as_str(), Some("v002-next"));
+        assert_eq!(
+            cargo_toml["dev-dependencies"]["tempfile"].as_str(),
+            Some("3.19.1")
+        );
+        assert!(cargo_toml.contains_key("bin"));
+        Ok(())
+    }
+}
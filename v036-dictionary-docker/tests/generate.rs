@@ -0,0 +1,65 @@
+use nanuak_core::prompt::PromptAnswer;
+use nanuak_core::prompt::get_remaining_answer_count;
+use nanuak_core::prompt::replay_answers;
+use v036_dictionary_docker::driver::drive;
+use v036_dictionary_docker::generate::ProposedEdit;
+use v036_dictionary_docker::generate_next_version_state::GenerateNextVersionState;
+
+fn answer(prompt: &str, answer: serde_json::Value) -> PromptAnswer {
+    PromptAnswer {
+        prompt: prompt.to_string(),
+        answer,
+        went_back: false,
+    }
+}
+
+/// The template has dev-dependencies, which the copy has to keep while its package is renamed.
+#[tokio::test]
+async fn generating_from_a_crate_with_dev_dependencies_applies_the_edits() -> eyre::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let workspace_dir = dir.path().join("Nanuak-Dictionary");
+    let template_dir = workspace_dir.join("v001-template");
+    tokio::fs::create_dir_all(template_dir.join("src")).await?;
+    tokio::fs::write(
+        workspace_dir.join("Cargo.toml"),
+        "[workspace]\nresolver = \"2\"\nmembers = [\"v001-template\"]\n\n[workspace.dependencies]\n",
+    )
+    .await?;
+    tokio::fs::write(
+        template_dir.join("Cargo.toml"),
+        "[package]\nname = \"v001-template\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[dependencies]\neyre = \"0.6.12\"\n\n[dev-dependencies]\ntempfile = \"3.16.0\"\n",
+    )
+    .await?;
+    tokio::fs::write(template_dir.join("src/main.rs"), "fn main() {}\n").await?;
+
+    replay_answers(vec![
+        answer("Enter the name for version v002", "v002-next".into()),
+        answer("Apply this edit to src/main.rs?", true.into()),
+    ]);
+    let state = drive(GenerateNextVersionState::ChooseNextVersionName {
+        workspace_dir: workspace_dir.clone(),
+        template_version_name: "v001-template".to_string(),
+        proposed_edits: vec![ProposedEdit {
+            path: "src/main.rs".to_string(),
+            contents: "fn main() {\n    println!(\"next\");\n}\n".to_string(),
+        }],
+    })
+    .await?;
+    assert_eq!(state, GenerateNextVersionState::Done);
+    assert_eq!(get_remaining_answer_count(), 0);
+
+    let next_dir = workspace_dir.join("v002-next");
+    let cargo_toml: toml::Table =
+        toml::from_str(&tokio::fs::read_to_string(next_dir.join("Cargo.toml")).await?)?;
+    assert_eq!(cargo_toml["package"]["name"].as_str(), Some("v002-next"));
+    assert_eq!(
+        cargo_toml["dev-dependencies"]["tempfile"].as_str(),
+        Some("3.16.0")
+    );
+    assert!(
+        tokio::fs::read_to_string(next_dir.join("src/main.rs"))
+            .await?
+            .contains("next")
+    );
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use nanuak_core::prompt::PromptAnswer;
+use nanuak_core::prompt::get_remaining_answer_count;
+use v036_dictionary_docker::create_new_version_state::CreateNewVersionState;
+use v036_dictionary_docker::replay::ANSWERS_FILE_NAME;
+use v036_dictionary_docker::replay::RECORDING_INFO_FILE_NAME;
+use v036_dictionary_docker::replay::RecordingInfo;
+use v036_dictionary_docker::replay::drive_replay;
+use v036_dictionary_docker::replay::start_replay;
+use v036_dictionary_docker::state::DictionaryApplicationState;
+use v036_dictionary_docker::state::State;
+
+async fn write_answers(recording_dir: &Path, answers: Vec<PromptAnswer>) -> eyre::Result<()> {
+    let lines = answers
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    tokio::fs::write(
+        recording_dir.join(ANSWERS_FILE_NAME),
+        format!("{}\n", lines.join("\n")),
+    )
+    .await?;
+    Ok(())
+}
+
+fn answer(prompt: &str, answer: impl Into<serde_json::Value>) -> PromptAnswer {
+    PromptAnswer {
+        prompt: prompt.to_string(),
+        answer: answer.into(),
+        went_back: false,
+    }
+}
+
+/// The template version is picked by its path in the recorded workspace, which the replay has
+/// to find in its copy instead.
+#[tokio::test]
+async fn version_picked_in_the_recorded_workspace_is_found_in_the_copy() -> eyre::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let workspace_dir = tokio::fs::canonicalize(dir.path())
+        .await?
+        .join("Nanuak-Dictionary");
+    let version_dir = workspace_dir.join("v001-good");
+    tokio::fs::create_dir_all(version_dir.join("src")).await?;
+    tokio::fs::write(
+        workspace_dir.join("Cargo.toml"),
+        "[workspace]\nresolver = \"2\"\nmembers = [\"v001-good\"]\n\n[workspace.dependencies]\n",
+    )
+    .await?;
+    tokio::fs::write(
+        version_dir.join("Cargo.toml"),
+        "[package]\nname = \"v001-good\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .await?;
+    tokio::fs::write(version_dir.join("src/main.rs"), "fn main() {}\n").await?;
+
+    let recording_dir = dir.path().join("recording");
+    tokio::fs::create_dir_all(&recording_dir).await?;
+    tokio::fs::write(
+        recording_dir.join(RECORDING_INFO_FILE_NAME),
+        serde_json::to_string_pretty(&RecordingInfo {
+            recorded_at: chrono::Utc::now(),
+            workspace_dir: Some(workspace_dir.clone()),
+        })?,
+    )
+    .await?;
+    let menu = "Choose an action";
+    write_answers(
+        &recording_dir,
+        vec![
+            answer(
+                menu,
+                DictionaryApplicationState::CreateNewVersion(CreateNewVersionState::default())
+                    .describe(),
+            ),
+            answer(
+                "Choose a version to copy",
+                version_dir.display().to_string(),
+            ),
+            answer("Enter the name for version v002", "v002-good"),
+            answer(menu, DictionaryApplicationState::Quit.describe()),
+        ],
+    )
+    .await?;
+
+    let replay = start_replay(&recording_dir, None).await?;
+    assert_ne!(replay.workspace_copy_dir, workspace_dir);
+    let state = drive_replay(&replay).await?;
+    assert_eq!(state, DictionaryApplicationState::Done);
+    assert_eq!(get_remaining_answer_count(), 0);
+    assert!(replay.workspace_copy_dir.join("v002-good").exists());
+    assert!(!workspace_dir.join("v002-good").exists());
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use v036_dictionary_docker::lookup_history::HISTORY_FILE_NAME;
+use v036_dictionary_docker::quiz::QUIZ_PROGRESS_FILE_NAME;
+use v036_dictionary_docker::quiz::QuizProgress;
+use v036_dictionary_docker::replay::check_replay;
+use v036_dictionary_docker::replay::drive_replay;
+use v036_dictionary_docker::replay::start_replay;
+use v036_dictionary_docker::state::DictionaryApplicationState;
+
+fn get_fixture_dir(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Looks up a word then takes a quiz, both of which read and write the data dir, and the quiz
+/// reads the clock.
+#[tokio::test]
+async fn recorded_define_and_quiz_session_replays() -> eyre::Result<()> {
+    let fixture_dir = get_fixture_dir("define-and-quiz");
+    let replay = start_replay(
+        &fixture_dir.join("recording"),
+        Some(fixture_dir.join("workspace")),
+    )
+    .await?;
+    let state = drive_replay(&replay).await?;
+    assert_eq!(state, DictionaryApplicationState::Done);
+    check_replay(&replay).await?;
+
+    assert!(replay.data_dir.join(HISTORY_FILE_NAME).exists());
+    assert!(replay.data_dir.join(QUIZ_PROGRESS_FILE_NAME).exists());
+    let progress = QuizProgress::read().await?;
+    let reviewed_at = progress
+        .cards
+        .values()
+        .map(|x| x.last_reviewed_at)
+        .collect::<Vec<_>>();
+    assert_eq!(reviewed_at, vec![Some(replay.recording_info.recorded_at)]);
+    Ok(())
+}